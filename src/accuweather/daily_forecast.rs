@@ -31,7 +31,6 @@ pub struct Sun {
 pub struct Moon {
     pub rise: Option<DateTime<Local>>,
     pub set: Option<DateTime<Local>>,
-    pub phase: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Hash, PartialEq)]
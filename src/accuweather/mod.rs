@@ -3,92 +3,427 @@ mod location;
 
 pub mod hourly_forecast;
 
-use crate::accuweather::daily_forecast::DailyForecast;
-use crate::accuweather::hourly_forecast::HourlyForecast;
 use crate::accuweather::location::Location;
-use crate::state::state;
-use std::cell::{RefCell};
+use crate::forecast::{self, Condition, DailyForecast, HourlyForecast};
+use crate::astronomy;
+use crate::state::{state, update_state, AccuWeatherLocationKey};
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::fs;
 
 const GEOPOSITION_SEARCH_URL: &str =
     "http://dataservice.accuweather.com/locations/v1/cities/geoposition/search";
 const DAILY_FORECAST_URL: &str = "http://dataservice.accuweather.com/forecasts/v1/daily/5day";
-const HOURLY_FORECAST_URL: &str = "http://dataservice.accuweather.com/forecasts/v1/hourly/12hour";
+const HOURLY_FORECAST_URL: &str = "http://dataservice.accuweather.com/forecasts/v1/hourly";
+
+/// The free tier's calls per day.
+const DEFAULT_DAILY_BUDGET: u32 = 50;
+
+/// Never refresh more often than this, however much quota is left.
+const MIN_CADENCE_MINUTES: i64 = 30;
+
+/// Daily and hourly forecasts are refreshed together.
+const CALLS_PER_REFRESH: u32 = 2;
+
+/// Calls made today, kept apart from the hand-edited config so that saving
+/// it after every call doesn't rewrite that.
+const USAGE_FILE: &str = "accuweather-usage.json";
+
+const BACKOFF_START_MINUTES: i64 = 5;
+const BACKOFF_MAX_MINUTES: i64 = 6 * 60;
 
 pub struct AccuWeatherClient {
     location_key: RefCell<Option<String>>,
+    /// Calls made on the given UTC day, the period AccuWeather's quota uses.
+    usage: Cell<(NaiveDate, u32)>,
+    /// The last `RateLimit-Remaining` the API reported, and the UTC day it
+    /// applies to.
+    remaining: Cell<Option<(NaiveDate, u32)>>,
+    failures: Cell<u32>,
+    backoff_until: Cell<Option<DateTime<Utc>>>,
 }
 
 impl AccuWeatherClient {
     pub fn new() -> Self {
         Self {
             location_key: RefCell::new(None),
+            usage: Cell::new(saved_usage().unwrap_or((Utc::now().date_naive(), 0))),
+            remaining: Cell::new(None),
+            failures: Cell::new(0),
+            backoff_until: Cell::new(None),
         }
     }
 
+    /// Spreads what is left of today's budget over the rest of the day.
+    /// While backing off it holds off entirely: the cadence is measured
+    /// from the last success, so any finite wait could already be over.
+    pub fn cadence(&self) -> Duration {
+        let now = Utc::now();
+        if self.backoff_until.get().is_some_and(|until| now < until) {
+            return Duration::max_value();
+        }
+
+        let (day, used) = self.usage.get();
+        let used = if day == now.date_naive() { used } else { 0 };
+        let mut remaining = daily_budget().saturating_sub(used);
+        if let Some((day, reported)) = self.remaining.get() {
+            if day == now.date_naive() {
+                remaining = remaining.min(reported);
+            }
+        }
+
+        let reset = (now.date_naive() + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .map(|e| e.and_utc())
+            .unwrap_or(now);
+
+        adaptive_cadence(remaining, reset - now)
+    }
+
+    /// Makes a counted API call, refusing to while backing off after a
+    /// failure or once today's budget is spent.
+    async fn get<T: DeserializeOwned>(&self, url: &str, query: &[(&str, String)]) -> Result<T, anyhow::Error> {
+        let now = Utc::now();
+        if let Some(until) = self.backoff_until.get() {
+            if now < until {
+                return Err(anyhow!("accuweather: backing off until {}", until));
+            }
+        }
+
+        let used = self.calls_today(now.date_naive());
+        let budget = daily_budget();
+        if used >= budget {
+            return Err(anyhow!("accuweather: daily budget of {} calls spent", budget));
+        }
+
+        self.record_call(now.date_naive(), used + 1);
+
+        match self.request(url, query, now).await {
+            Ok(value) => {
+                self.failures.set(0);
+                self.backoff_until.set(None);
+                Ok(value)
+            }
+            Err(err) => {
+                // whatever went wrong, retrying every pass would only spend
+                // the budget on the same failure.
+                let failures = self.failures.get() + 1;
+                self.failures.set(failures);
+                let until = now + backoff(failures);
+                self.backoff_until.set(Some(until));
+                Err(anyhow!("accuweather: {}, backing off until {}", err, until))
+            }
+        }
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+        now: DateTime<Utc>,
+    ) -> Result<T, anyhow::Error> {
+        let response = reqwest::Client::new().get(url).query(query).send().await?;
+
+        if let Some(remaining) = response
+            .headers()
+            .get("RateLimit-Remaining")
+            .and_then(|e| e.to_str().ok())
+            .and_then(|e| e.parse().ok())
+        {
+            self.remaining.set(Some((now.date_naive(), remaining)));
+        }
+
+        if matches!(
+            response.status(),
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            return Err(anyhow!("{} (quota exhausted?)", response.status()));
+        }
+
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Calls made today, including any made by other processes since.
+    fn calls_today(&self, today: NaiveDate) -> u32 {
+        [Some(self.usage.get()), saved_usage()]
+            .into_iter()
+            .flatten()
+            .filter(|(day, _)| *day == today)
+            .map(|(_, calls)| calls)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Sets today's call count and saves it.
+    fn record_call(&self, today: NaiveDate, used: u32) {
+        self.usage.set((today, used));
+
+        if let Err(err) = save_usage(today, used) {
+            println!("accuweather: unable to save usage: {}", err);
+        }
+    }
+
+    /// Looked up once per location and saved to the config, since the
+    /// geoposition search counts against the same quota as forecasts.
     pub async fn get_location_key(&self) -> Result<String, anyhow::Error> {
         let state = state();
+        let api_key = api_key()?;
 
         if let Some(location_key) = &*self.location_key.borrow() {
             return Ok(location_key.clone());
         }
 
-        let location: Location = reqwest::Client::new()
-            .get(GEOPOSITION_SEARCH_URL)
-            .query(&[
-                ("apikey", state.accuweather.api_key),
-                (
-                    "q",
-                    format!("{},{}", state.location.lat, state.location.lon),
-                ),
-            ])
-            .send()
-            .await?
-            .json()
+        let saved = state.accuweather.as_ref().and_then(|e| e.location_key.clone());
+        if let Some(saved) = saved {
+            if saved.lat == state.location.lat && saved.lon == state.location.lon {
+                self.location_key.borrow_mut().replace(saved.key.clone());
+                return Ok(saved.key);
+            }
+        }
+
+        let location: Location = self
+            .get(
+                GEOPOSITION_SEARCH_URL,
+                &[
+                    ("apikey", api_key),
+                    (
+                        "q",
+                        format!("{},{}", state.location.lat, state.location.lon),
+                    ),
+                ],
+            )
             .await?;
 
         self.location_key.borrow_mut().replace(location.key.clone());
 
+        let saved = AccuWeatherLocationKey {
+            lat: state.location.lat,
+            lon: state.location.lon,
+            key: location.key.clone(),
+        };
+        if let Err(err) = update_state(|update| {
+            if let Some(accuweather) = &mut update.accuweather {
+                accuweather.location_key = Some(saved);
+            }
+        }) {
+            println!("accuweather: unable to save location key: {}", err);
+        }
+
         Ok(location.key)
     }
 
     pub async fn daily_forecast(&self) -> Result<Vec<DailyForecast>, anyhow::Error> {
-        let state = state();
+        let api_key = api_key()?;
         let location_key = self.get_location_key().await?;
 
         let url = format!("{}/{}", DAILY_FORECAST_URL, location_key);
 
-        let forecast: daily_forecast::Envelope = reqwest::Client::new()
-            //let forecast: Value= reqwest::Client::new()
-            .get(url)
-            .query(&[
-                ("apikey", state.accuweather.api_key),
-                ("details", "true".to_string()),
-            ])
-            .send()
-            .await?
-            .json()
+        let forecast: daily_forecast::Envelope = self
+            .get(
+                &url,
+                &[("apikey", api_key), ("details", "true".to_string())],
+            )
             .await?;
 
-        Ok(forecast.daily_forecasts)
+        Ok(forecast.daily_forecasts.into_iter().map(Into::into).collect())
     }
 
     pub async fn hourly_forecasts(&self) -> Result<Vec<HourlyForecast>, anyhow::Error> {
-        let state = state();
+        let api_key = api_key()?;
         let location_key = self.get_location_key().await?;
 
-        let url = format!("{}/{}", HOURLY_FORECAST_URL, location_key);
-
-        let forecast: hourly_forecast::Envelope = reqwest::Client::new()
-            .get(url)
-            .query(&[
-                ("apikey", state.accuweather.api_key),
-                ("details", "true".to_string()),
-            ])
-            .send()
-            .await?
-            .json()
+        let hours = state().display.hourly_hours;
+        let period = if hours > 12 { "24hour" } else { "12hour" };
+        let url = format!("{}/{}/{}", HOURLY_FORECAST_URL, period, location_key);
+
+        let forecast: hourly_forecast::Envelope = self
+            .get(
+                &url,
+                &[("apikey", api_key), ("details", "true".to_string())],
+            )
             .await?;
 
-        Ok(forecast.0)
+        Ok(forecast.0.into_iter().take(hours).map(Into::into).collect())
+    }
+}
+
+fn adaptive_cadence(remaining: u32, until_reset: Duration) -> Duration {
+    let minimum = Duration::minutes(MIN_CADENCE_MINUTES);
+    let refreshes = remaining / CALLS_PER_REFRESH;
+    if refreshes == 0 {
+        return until_reset.max(minimum);
+    }
+    (until_reset / refreshes as i32).max(minimum)
+}
+
+/// Doubles with each consecutive failure, up to a cap.
+fn backoff(failures: u32) -> Duration {
+    let minutes = BACKOFF_START_MINUTES.saturating_mul(1 << failures.saturating_sub(1).min(16));
+    Duration::minutes(minutes.min(BACKOFF_MAX_MINUTES))
+}
+
+/// Calls made on a UTC day, the period AccuWeather's quota uses.
+#[derive(Serialize, Deserialize)]
+struct Usage {
+    date: NaiveDate,
+    calls: u32,
+}
+
+/// Usage as last saved, which may be from another process.
+fn saved_usage() -> Option<(NaiveDate, u32)> {
+    let data = fs::read_to_string(USAGE_FILE).ok()?;
+    let usage: Usage = serde_json::from_str(&data).ok()?;
+    Some((usage.date, usage.calls))
+}
+
+/// Written beside and renamed over the old file, like the config.
+fn save_usage(date: NaiveDate, calls: u32) -> Result<(), anyhow::Error> {
+    let staged = format!("{}.tmp", USAGE_FILE);
+    fs::write(&staged, serde_json::to_string(&Usage { date, calls })?)?;
+    fs::rename(&staged, USAGE_FILE)?;
+    Ok(())
+}
+
+fn daily_budget() -> u32 {
+    state()
+        .accuweather
+        .and_then(|e| e.daily_budget)
+        .unwrap_or(DEFAULT_DAILY_BUDGET)
+}
+
+fn api_key() -> Result<String, anyhow::Error> {
+    state()
+        .accuweather
+        .map(|e| e.api_key)
+        .ok_or(anyhow!("accuweather: no api_key configured"))
+}
+
+/// Maps AccuWeather's icon numbers, see
+/// <https://developer.accuweather.com/weather-icons>. 33 and up are the
+/// night versions.
+fn condition(icon: u8) -> Condition {
+    match icon {
+        1 | 5 => Condition::Clear,
+        2..=4 => Condition::PartlyCloudy,
+        6 => Condition::MostlyCloudy,
+        7 | 8 => Condition::Cloudy,
+        11 => Condition::Fog,
+        13 | 14 => Condition::Showers,
+        12 | 18 => Condition::Rain,
+        15 => Condition::Thunderstorms,
+        16 | 17 => Condition::ScatteredThunderstorms,
+        19..=21 | 43 => Condition::Flurries,
+        22 | 23 | 44 => Condition::Snow,
+        24 => Condition::Ice,
+        25 | 26 | 29 => Condition::Sleet,
+        30 => Condition::Hot,
+        31 => Condition::Cold,
+        32 => Condition::Windy,
+        33 | 34 => Condition::ClearNight,
+        35..=37 => Condition::PartlyCloudyNight,
+        38 => Condition::MostlyCloudyNight,
+        39 | 40 => Condition::ShowersNight,
+        41 | 42 => Condition::ScatteredThunderstormsNight,
+        _ => Condition::Unknown,
+    }
+}
+
+impl From<daily_forecast::DailyForecast> for DailyForecast {
+    fn from(value: daily_forecast::DailyForecast) -> Self {
+        // computed rather than parsed from AccuWeather's phase names.
+        let (phase, illumination) =
+            astronomy::moon_phase((value.date + Duration::hours(12)).with_timezone(&Utc));
+
+        Self {
+            date: value.date,
+            sun: Some(forecast::Sun {
+                rise: value.sun.rise,
+                set: value.sun.set,
+                dawn: None,
+                dusk: None,
+            }),
+            moon: Some(forecast::Moon {
+                rise: value.moon.rise,
+                set: value.moon.set,
+                phase,
+                illumination,
+            }),
+            temperature: forecast::Temperature {
+                minimum: value.temperature.minimum.value,
+                maximum: value.temperature.maximum.value,
+            },
+            day: value.day.into(),
+            night: value.night.into(),
+        }
+    }
+}
+
+impl From<daily_forecast::Details> for forecast::Details {
+    fn from(value: daily_forecast::Details) -> Self {
+        let wind = |wind: daily_forecast::Wind| forecast::Wind {
+            speed: wind.speed.value,
+            degrees: wind.direction.degrees,
+        };
+
+        Self {
+            condition: condition(value.icon),
+            phrase: value
+                .long_phrase
+                .replace("; check AccuWeather frequently", ""),
+            precipitation_probability: value.precipitation_probability,
+            rain: value.rain.value,
+            snow: value.snow.value,
+            ice: value.ice.value,
+            wind: Some(wind(value.wind)),
+            wind_gust: Some(wind(value.wind_gust)),
+        }
+    }
+}
+
+impl From<hourly_forecast::HourlyForecast> for HourlyForecast {
+    fn from(value: hourly_forecast::HourlyForecast) -> Self {
+        Self {
+            date_time: value.date_time,
+            temperature: value.temperature.value,
+            precipitation_probability: value.precipitation_probability,
+            condition: condition(value.weather_icon),
+            phrase: value.icon_phrase,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::accuweather::{adaptive_cadence, backoff, condition};
+    use crate::forecast::Condition;
+    use chrono::Duration;
+
+    #[test]
+    fn conditions() {
+        assert_eq!(condition(3), Condition::PartlyCloudy);
+        assert_eq!(condition(4), Condition::PartlyCloudy);
+        assert_eq!(condition(6), Condition::MostlyCloudy);
+        assert_eq!(condition(35), Condition::PartlyCloudyNight);
+        assert_eq!(condition(3).at_night(), condition(35));
+        assert_eq!(condition(30), Condition::Hot);
+        assert_eq!(condition(31), Condition::Cold);
+        assert_eq!(condition(9), Condition::Unknown);
+    }
+
+    #[test]
+    fn quota() {
+        // plenty left: the minimum cadence.
+        assert_eq!(adaptive_cadence(50, Duration::hours(10)), Duration::minutes(30));
+        // 10 calls is 5 refreshes over 10 hours.
+        assert_eq!(adaptive_cadence(10, Duration::hours(10)), Duration::hours(2));
+        assert_eq!(adaptive_cadence(1, Duration::hours(10)), Duration::hours(10));
+
+        assert_eq!(backoff(1), Duration::minutes(5));
+        assert_eq!(backoff(3), Duration::minutes(20));
+        assert_eq!(backoff(30), Duration::hours(6));
     }
 }
@@ -0,0 +1,123 @@
+/// US EPA PM2.5 breakpoints (2024 revision) as (concentration low, high,
+/// index low, high), concentrations in µg/m³.
+const BREAKPOINTS: [(f64, f64, f64, f64); 6] = [
+    (0.0, 9.0, 0.0, 50.0),
+    (9.1, 35.4, 51.0, 100.0),
+    (35.5, 55.4, 101.0, 150.0),
+    (55.5, 125.4, 151.0, 200.0),
+    (125.5, 225.4, 201.0, 300.0),
+    (225.5, 325.4, 301.0, 500.0),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Category {
+    Good,
+    Moderate,
+    UnhealthyForSensitiveGroups,
+    Unhealthy,
+    VeryUnhealthy,
+    Hazardous,
+}
+
+impl Category {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::Good => "Good",
+            Category::Moderate => "Moderate",
+            Category::UnhealthyForSensitiveGroups => "Unhealthy for Sensitive Groups",
+            Category::Unhealthy => "Unhealthy",
+            Category::VeryUnhealthy => "Very Unhealthy",
+            Category::Hazardous => "Hazardous",
+        }
+    }
+
+    fn from_index(index: u16) -> Self {
+        match index {
+            0..=50 => Category::Good,
+            51..=100 => Category::Moderate,
+            101..=150 => Category::UnhealthyForSensitiveGroups,
+            151..=200 => Category::Unhealthy,
+            201..=300 => Category::VeryUnhealthy,
+            _ => Category::Hazardous,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AqiIndex {
+    pub value: u16,
+    pub category: Category,
+}
+
+/// Converts a PM2.5 concentration in µg/m³ to the US EPA AQI.
+pub fn pm2_5_to_aqi(pm2_5: f64) -> AqiIndex {
+    // the EPA truncates to one decimal before looking up the breakpoint.
+    let pm2_5 = (pm2_5.max(0.0) * 10.0).floor() / 10.0;
+
+    let value = BREAKPOINTS
+        .iter()
+        .find(|(_, c_high, _, _)| pm2_5 <= *c_high)
+        .map(|(c_low, c_high, i_low, i_high)| {
+            ((i_high - i_low) / (c_high - c_low)) * (pm2_5 - c_low) + i_low
+        })
+        .unwrap_or(500.0)
+        .round() as u16;
+
+    AqiIndex {
+        value,
+        category: Category::from_index(value),
+    }
+}
+
+/// The US EPA correction for PurpleAir sensors, taking the CF=1 PM2.5
+/// reading and relative humidity. PurpleAir overreads, especially in
+/// humid air.
+pub fn epa_correction(pm2_5_cf_1: f64, humidity: f64) -> f64 {
+    let x = pm2_5_cf_1;
+    let rh = humidity;
+
+    let corrected = if x < 30.0 {
+        0.524 * x - 0.0862 * rh + 5.75
+    } else if x < 50.0 {
+        let weight = x / 20.0 - 3.0 / 2.0;
+        (0.786 * weight + 0.524 * (1.0 - weight)) * x - 0.0862 * rh + 5.75
+    } else if x < 210.0 {
+        0.786 * x - 0.0862 * rh + 5.75
+    } else if x < 260.0 {
+        let weight = x / 50.0 - 21.0 / 5.0;
+        (0.69 * weight + 0.786 * (1.0 - weight)) * x - 0.0862 * rh * (1.0 - weight)
+            + 2.966 * weight
+            + 5.75 * (1.0 - weight)
+            + 8.84e-4 * x.powi(2) * weight
+    } else {
+        2.966 + 0.69 * x + 8.84e-4 * x.powi(2)
+    };
+
+    corrected.max(0.0)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::aqi::{epa_correction, pm2_5_to_aqi, Category};
+
+    #[test]
+    fn breakpoints() {
+        assert_eq!(pm2_5_to_aqi(0.0).value, 0);
+        assert_eq!(pm2_5_to_aqi(9.0).value, 50);
+        assert_eq!(pm2_5_to_aqi(12.0).value, 56);
+        assert_eq!(pm2_5_to_aqi(35.5).value, 101);
+        assert_eq!(
+            pm2_5_to_aqi(35.5).category,
+            Category::UnhealthyForSensitiveGroups
+        );
+        assert_eq!(pm2_5_to_aqi(900.0).value, 500);
+        assert_eq!(pm2_5_to_aqi(900.0).category, Category::Hazardous);
+    }
+
+    #[test]
+    fn correction() {
+        assert!((epa_correction(20.0, 50.0) - 11.92).abs() < 0.01);
+        assert!((epa_correction(100.0, 40.0) - 80.902).abs() < 0.01);
+        assert_eq!(epa_correction(0.0, 100.0), 0.0);
+    }
+}
@@ -0,0 +1,282 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use std::f64::consts::PI;
+
+/// Days from the Unix epoch to J2000.0, 2000-01-01 12:00 UTC.
+const J2000_UNIX_DAYS: f64 = 10957.5;
+
+const OBLIQUITY: f64 = 23.4397;
+
+/// The sun's center this far below the horizon is sunrise or sunset,
+/// allowing for refraction and the size of the disc.
+const SUNRISE_ALTITUDE: f64 = -0.833;
+const CIVIL_TWILIGHT_ALTITUDE: f64 = -6.0;
+
+/// Geocentric altitude of the moon's center at moonrise, allowing for
+/// parallax, refraction and the size of the disc.
+const MOONRISE_ALTITUDE: f64 = 0.125;
+const MOON_SAMPLE_MINUTES: i64 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MoonPhase {
+    New,
+    WaxingCrescent,
+    FirstQuarter,
+    WaxingGibbous,
+    Full,
+    WaningGibbous,
+    ThirdQuarter,
+    WaningCrescent,
+}
+
+impl MoonPhase {
+    /// Names the phase from the fraction of the lunar cycle, 0 being new.
+    /// Each named phase covers an eighth of the cycle, centered on it.
+    fn from_cycle(cycle: f64) -> Self {
+        match ((cycle * 8.0).round() as usize) % 8 {
+            0 => MoonPhase::New,
+            1 => MoonPhase::WaxingCrescent,
+            2 => MoonPhase::FirstQuarter,
+            3 => MoonPhase::WaxingGibbous,
+            4 => MoonPhase::Full,
+            5 => MoonPhase::WaningGibbous,
+            6 => MoonPhase::ThirdQuarter,
+            _ => MoonPhase::WaningCrescent,
+        }
+    }
+}
+
+/// Sun events for one local date. Any of them can be missing near the
+/// poles, when the sun never crosses the relevant altitude.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SunTimes {
+    pub dawn: Option<DateTime<Local>>,
+    pub rise: Option<DateTime<Local>>,
+    pub set: Option<DateTime<Local>>,
+    pub dusk: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoonTimes {
+    pub rise: Option<DateTime<Local>>,
+    pub set: Option<DateTime<Local>>,
+    pub phase: MoonPhase,
+    /// Fraction of the disc that is lit, 0 to 1.
+    pub illumination: f32,
+}
+
+pub fn sun(date: NaiveDate, lat: f64, lon: f64) -> SunTimes {
+    let local = |time: Option<DateTime<Utc>>| time.map(|e| e.with_timezone(&Local));
+
+    let (rise, set) = sun_events(date, lat, lon, SUNRISE_ALTITUDE);
+    let (dawn, dusk) = sun_events(date, lat, lon, CIVIL_TWILIGHT_ALTITUDE);
+
+    SunTimes {
+        dawn: local(dawn),
+        rise: local(rise),
+        set: local(set),
+        dusk: local(dusk),
+    }
+}
+
+pub fn moon(date: NaiveDate, lat: f64, lon: f64) -> MoonTimes {
+    let start = Local
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&date.and_time(NaiveTime::MIN)))
+        .with_timezone(&Utc);
+
+    let (rise, set) = moon_events(start, lat, lon);
+    let (phase, illumination) = moon_phase(start + Duration::hours(12));
+
+    MoonTimes {
+        rise: rise.map(|e| e.with_timezone(&Local)),
+        set: set.map(|e| e.with_timezone(&Local)),
+        phase,
+        illumination,
+    }
+}
+
+/// The moon's phase and lit fraction at `time`.
+pub fn moon_phase(time: DateTime<Utc>) -> (MoonPhase, f32) {
+    let d = days(time);
+    let (moon_longitude, _) = moon_ecliptic(d);
+
+    let elongation = (moon_longitude - sun_longitude(d)).rem_euclid(2.0 * PI);
+    let illumination = (1.0 - elongation.cos()) / 2.0;
+
+    (
+        MoonPhase::from_cycle(elongation / (2.0 * PI)),
+        illumination as f32,
+    )
+}
+
+fn days(time: DateTime<Utc>) -> f64 {
+    time.timestamp() as f64 / 86400.0 - J2000_UNIX_DAYS
+}
+
+fn from_days(d: f64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(((d + J2000_UNIX_DAYS) * 86400.0).round() as i64, 0)
+}
+
+fn solar_mean_anomaly(d: f64) -> f64 {
+    (357.5291 + 0.98560028 * d).to_radians()
+}
+
+fn sun_longitude(d: f64) -> f64 {
+    let m = solar_mean_anomaly(d);
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    m + center.to_radians() + 102.9372_f64.to_radians() + PI
+}
+
+/// Rising and setting times of the sun through `altitude` degrees, from
+/// the sunrise equation around the solar noon nearest the date.
+fn sun_events(
+    date: NaiveDate,
+    lat: f64,
+    lon: f64,
+    altitude: f64,
+) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let epoch = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+    let n = (date - epoch).num_days() as f64;
+
+    let mean_noon = n - lon / 360.0;
+    let m = solar_mean_anomaly(mean_noon);
+    let longitude = sun_longitude(mean_noon);
+    let transit = mean_noon + 0.0053 * m.sin() - 0.0069 * (2.0 * longitude).sin();
+
+    let declination = (longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+    let lat = lat.to_radians();
+    let cos_hour_angle = (altitude.to_radians().sin() - lat.sin() * declination.sin())
+        / (lat.cos() * declination.cos());
+
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return (None, None);
+    }
+
+    let hour_angle = cos_hour_angle.acos() / (2.0 * PI);
+    (from_days(transit - hour_angle), from_days(transit + hour_angle))
+}
+
+/// Ecliptic longitude and latitude of the moon, in radians.
+fn moon_ecliptic(d: f64) -> (f64, f64) {
+    let mean_longitude = (218.316 + 13.176396 * d).to_radians();
+    let mean_anomaly = (134.963 + 13.064993 * d).to_radians();
+    let mean_distance = (93.272 + 13.229350 * d).to_radians();
+
+    (
+        mean_longitude + 6.289_f64.to_radians() * mean_anomaly.sin(),
+        5.128_f64.to_radians() * mean_distance.sin(),
+    )
+}
+
+fn moon_altitude(time: DateTime<Utc>, lat: f64, lon: f64) -> f64 {
+    let d = days(time);
+    let (l, b) = moon_ecliptic(d);
+    let e = OBLIQUITY.to_radians();
+
+    let right_ascension = (l.sin() * e.cos() - b.tan() * e.sin()).atan2(l.cos());
+    let declination = (b.sin() * e.cos() + b.cos() * e.sin() * l.sin()).asin();
+
+    let sidereal = (280.16 + 360.9856235 * d + lon).to_radians();
+    let hour_angle = sidereal - right_ascension;
+
+    let lat = lat.to_radians();
+    (lat.sin() * declination.sin() + lat.cos() * declination.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
+
+/// Moonrise and moonset in the day from `start`, found by stepping through
+/// the day and interpolating where the altitude crosses the horizon. The
+/// moon skips a rise or set roughly once a month.
+fn moon_events(
+    start: DateTime<Utc>,
+    lat: f64,
+    lon: f64,
+) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    let step = Duration::minutes(MOON_SAMPLE_MINUTES);
+    let samples = 24 * 60 / MOON_SAMPLE_MINUTES;
+
+    let mut rise = None;
+    let mut set = None;
+
+    let mut previous = moon_altitude(start, lat, lon) - MOONRISE_ALTITUDE;
+    for i in 1..=samples {
+        let time = start + step * i as i32;
+        let altitude = moon_altitude(time, lat, lon) - MOONRISE_ALTITUDE;
+
+        if previous.signum() != altitude.signum() {
+            let fraction = previous / (previous - altitude);
+            let crossing =
+                time - step + Duration::seconds((fraction * step.num_seconds() as f64) as i64);
+            if altitude > 0.0 {
+                rise.get_or_insert(crossing);
+            } else {
+                set.get_or_insert(crossing);
+            }
+        }
+        previous = altitude;
+    }
+
+    (rise, set)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NEW_YORK: (f64, f64) = (40.7128, -74.0060);
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    fn within(actual: DateTime<Utc>, expected: &str, minutes: i64) -> bool {
+        (actual - utc(expected)).num_minutes().abs() <= minutes
+    }
+
+    #[test]
+    fn solstice_sun() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let (rise, set) = sun_events(date, NEW_YORK.0, NEW_YORK.1, SUNRISE_ALTITUDE);
+        assert!(within(rise.unwrap(), "2024-06-21T05:25:00-04:00", 3));
+        assert!(within(set.unwrap(), "2024-06-21T20:31:00-04:00", 3));
+
+        let (dawn, dusk) = sun_events(date, NEW_YORK.0, NEW_YORK.1, CIVIL_TWILIGHT_ALTITUDE);
+        assert!(within(dawn.unwrap(), "2024-06-21T04:53:00-04:00", 3));
+        assert!(within(dusk.unwrap(), "2024-06-21T21:03:00-04:00", 3));
+
+        // no sunrise in the arctic winter.
+        let date = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_eq!(sun_events(date, 78.2, 15.6, SUNRISE_ALTITUDE), (None, None));
+    }
+
+    #[test]
+    fn phases() {
+        // the April 2024 eclipse.
+        let (phase, illumination) = moon_phase(utc("2024-04-08T18:21:00Z"));
+        assert_eq!(phase, MoonPhase::New);
+        assert!(illumination < 0.01);
+
+        let (phase, illumination) = moon_phase(utc("2024-06-22T01:08:00Z"));
+        assert_eq!(phase, MoonPhase::Full);
+        assert!(illumination > 0.99);
+
+        let (phase, _) = moon_phase(utc("2024-06-14T05:18:00Z"));
+        assert_eq!(phase, MoonPhase::FirstQuarter);
+    }
+
+    #[test]
+    fn full_moon_rises_at_sunset() {
+        let (rise, set) = moon_events(utc("2024-06-21T00:00:00-04:00"), NEW_YORK.0, NEW_YORK.1);
+        let (_, sunset) = sun_events(
+            NaiveDate::from_ymd_opt(2024, 6, 21).unwrap(),
+            NEW_YORK.0,
+            NEW_YORK.1,
+            SUNRISE_ALTITUDE,
+        );
+
+        assert!((rise.unwrap() - sunset.unwrap()).num_minutes().abs() < 90);
+        assert!(set.unwrap() < rise.unwrap());
+    }
+}
@@ -0,0 +1,247 @@
+use crate::calendar::Event;
+use crate::state::state;
+use chrono::{Datelike, Local, NaiveDate};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::Read;
+
+const CALENDAR_LABEL: &str = "Birthdays";
+
+/// Apple Contacts stores dates without a year as 1604.
+const UNKNOWN_YEAR: i32 = 1604;
+
+#[derive(Deserialize, Debug, Clone)]
+struct BirthdayList {
+    dates: Vec<BirthdayEntry>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BirthdayEntry {
+    name: String,
+    date: String,
+    #[serde(default)]
+    kind: Kind,
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    #[default]
+    Birthday,
+    Anniversary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Birthday {
+    pub name: String,
+    pub kind: Kind,
+    pub year: Option<i32>,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Birthday {
+    /// The next occurrence on or after `today`. February 29th falls on
+    /// the 28th in common years.
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<NaiveDate> {
+        let this_year = self.occurrence_in(today.year())?;
+        if this_year >= today {
+            Some(this_year)
+        } else {
+            self.occurrence_in(today.year() + 1)
+        }
+    }
+
+    fn occurrence_in(&self, year: i32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, self.month, self.day)
+            .or_else(|| NaiveDate::from_ymd_opt(year, self.month, self.day - 1))
+    }
+
+    /// Age, or years married, as of the given occurrence.
+    pub fn years_on(&self, date: NaiveDate) -> Option<i32> {
+        self.year.map(|year| date.year() - year).filter(|e| *e > 0)
+    }
+
+    pub fn summary(&self, date: NaiveDate) -> String {
+        match (self.kind, self.years_on(date)) {
+            (Kind::Birthday, Some(age)) => format!("{}'s birthday ({})", self.name, age),
+            (Kind::Birthday, None) => format!("{}'s birthday", self.name),
+            (Kind::Anniversary, Some(1)) => format!("{} anniversary (1 year)", self.name),
+            (Kind::Anniversary, Some(years)) => {
+                format!("{} anniversary ({} years)", self.name, years)
+            }
+            (Kind::Anniversary, None) => format!("{} anniversary", self.name),
+        }
+    }
+}
+
+pub struct BirthdayClient {}
+
+impl BirthdayClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub async fn events(&self) -> Result<Vec<Event>, anyhow::Error> {
+        let Some(state) = state().birthdays else {
+            return Ok(vec![]);
+        };
+
+        let mut file = File::open(&state.path)?;
+        let mut data = String::new();
+        file.read_to_string(&mut data)?;
+
+        let birthdays = if state.path.to_lowercase().ends_with(".vcf") {
+            parse_vcard(&data)?
+        } else {
+            parse_list(&data)?
+        };
+
+        let today = Local::now().date_naive();
+
+        Ok(birthdays
+            .iter()
+            .filter_map(|birthday| {
+                let date = birthday.next_occurrence(today)?;
+                Some(Event {
+                    summary: birthday.summary(date),
+                    date,
+                    time: None,
+                    calendar: Some(CALENDAR_LABEL.to_string()),
+                })
+            })
+            .collect())
+    }
+}
+
+fn parse_list(data: &str) -> Result<Vec<Birthday>, anyhow::Error> {
+    let list: BirthdayList = toml::from_str(data)?;
+
+    Ok(list
+        .dates
+        .into_iter()
+        .filter_map(|entry| {
+            let (year, month, day) = parse_date(&entry.date)?;
+            Some(Birthday {
+                name: entry.name,
+                kind: entry.kind,
+                year,
+                month,
+                day,
+            })
+        })
+        .collect())
+}
+
+fn parse_vcard(data: &str) -> Result<Vec<Birthday>, anyhow::Error> {
+    let mut birthdays = Vec::new();
+
+    for contact in ical::VcardParser::new(data.as_bytes()) {
+        let contact = contact?;
+
+        let Some(name) = contact
+            .properties
+            .iter()
+            .find(|e| e.name == "FN")
+            .and_then(|e| e.value.clone())
+        else {
+            continue;
+        };
+
+        for property in &contact.properties {
+            let kind = match property.name.as_str() {
+                "BDAY" => Kind::Birthday,
+                "ANNIVERSARY" | "X-ANNIVERSARY" => Kind::Anniversary,
+                _ => continue,
+            };
+
+            if let Some((year, month, day)) = property.value.as_deref().and_then(parse_date) {
+                birthdays.push(Birthday {
+                    name: name.clone(),
+                    kind,
+                    year,
+                    month,
+                    day,
+                });
+            }
+        }
+    }
+
+    Ok(birthdays)
+}
+
+/// Accepts `1980-03-14`, `19800314`, and the year-less `--03-14`, `--0314`
+/// and `03-14` forms. Any time component is ignored.
+fn parse_date(value: &str) -> Option<(Option<i32>, u32, u32)> {
+    let value = value.split('T').next()?;
+
+    if let Some(month_day) = value.strip_prefix("--") {
+        let month_day = month_day.replace('-', "");
+        return month_and_day(&month_day).map(|(month, day)| (None, month, day));
+    }
+
+    let digits = value.replace('-', "");
+    match digits.len() {
+        8 => {
+            let year: i32 = digits[0..4].parse().ok()?;
+            let (month, day) = month_and_day(&digits[4..])?;
+            let year = Some(year).filter(|e| *e != UNKNOWN_YEAR);
+            Some((year, month, day))
+        }
+        4 => month_and_day(&digits).map(|(month, day)| (None, month, day)),
+        _ => None,
+    }
+}
+
+fn month_and_day(digits: &str) -> Option<(u32, u32)> {
+    if digits.len() != 4 {
+        return None;
+    }
+    let month: u32 = digits[0..2].parse().ok()?;
+    let day: u32 = digits[2..4].parse().ok()?;
+    // validate against a leap year so February 29th is accepted.
+    NaiveDate::from_ymd_opt(2000, month, day)?;
+    Some((month, day))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::birthdays::{parse_date, parse_vcard, Birthday, Kind};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("1980-03-14"), Some((Some(1980), 3, 14)));
+        assert_eq!(parse_date("19800314T000000"), Some((Some(1980), 3, 14)));
+        assert_eq!(parse_date("--0314"), Some((None, 3, 14)));
+        assert_eq!(parse_date("1604-03-14"), Some((None, 3, 14)));
+        assert_eq!(parse_date("1980-02-30"), None);
+    }
+
+    #[test]
+    fn next_occurrence_and_age() {
+        let birthday = Birthday {
+            name: "Grandma".to_string(),
+            kind: Kind::Birthday,
+            year: Some(1948),
+            month: 2,
+            day: 29,
+        };
+
+        let today = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let next = birthday.next_occurrence(today).unwrap();
+        assert_eq!(next, NaiveDate::from_ymd_opt(2026, 2, 28).unwrap());
+        assert_eq!(birthday.summary(next), "Grandma's birthday (78)");
+    }
+
+    #[test]
+    fn vcard() {
+        let data = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Mom & Dad\r\nANNIVERSARY:19900616\r\nEND:VCARD\r\n";
+        let birthdays = parse_vcard(data).unwrap();
+        assert_eq!(birthdays.len(), 1);
+        assert_eq!(birthdays[0].kind, Kind::Anniversary);
+
+        let date = NaiveDate::from_ymd_opt(2026, 6, 16).unwrap();
+        assert_eq!(birthdays[0].summary(date), "Mom & Dad anniversary (36 years)");
+    }
+}
@@ -1,43 +1,45 @@
-use crate::state::state;
-use chrono::{NaiveDate};
+use crate::state::{state, CalendarUrl};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use regex::Regex;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use std::cell::RefCell;
+use std::collections::HashMap;
 
-pub struct CalendarClient {}
+/// The last successful download of a single calendar feed.
+#[derive(Debug, Clone, Default)]
+struct Feed {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    events: Vec<Event>,
+}
+
+pub struct CalendarClient {
+    feeds: RefCell<HashMap<String, Feed>>,
+}
 
 impl CalendarClient {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            feeds: RefCell::new(HashMap::new()),
+        }
     }
 
     pub async fn events(&self) -> Result<Vec<Event>, anyhow::Error> {
         let state = state().calendar;
         let mut events: Vec<Event> = Vec::new();
 
-        let parens = Regex::new( "\\(.*\\)")?;
-        for url in state.urls {
-            let result = reqwest::Client::new().get(url).send().await?.text().await?;
-
-            let bytes = &*result.into_bytes();
-            for line in ical::IcalParser::new(bytes).flatten() {
-                for event in line.events {
-                    let summary = event.properties.iter().find(|e| e.name == "SUMMARY");
-                    let date = event.properties.iter().find(|e| e.name == "DTSTART");
-                    if let (Some(summary), Some(date)) = (summary, date) {
-                        if let (Some(summary), Some(date)) = (&summary.value, &date.value) {
-                            let (date, _) =
-                                chrono::NaiveDate::parse_and_remainder(date, "%Y%m%d")
-                                    .unwrap();
-                            let summary = parens.replace_all(summary, "");
-
-                            if events.iter().find(|e| {
-                                e.summary == summary && e.date == date
-                            }).is_none() {
-                                events.push(Event {
-                                    summary: summary.to_string(),
-                                    date,
-                                });
-                            }
-                        }
+        for source in state.urls {
+            // a failing feed keeps whatever it last produced rather than
+            // taking the other calendars down with it.
+            if let Err(err) = self.refresh(&source).await {
+                println!("calendar {} failed: {}", source.url(), err);
+            }
+
+            if let Some(feed) = self.feeds.borrow().get(source.url()) {
+                for event in &feed.events {
+                    if !events.iter().any(|e| e.summary == event.summary && e.date == event.date) {
+                        events.push(event.clone());
                     }
                 }
             }
@@ -45,10 +47,115 @@ impl CalendarClient {
 
         Ok(events)
     }
+
+    async fn refresh(&self, source: &CalendarUrl) -> Result<(), anyhow::Error> {
+        let mut request = reqwest::Client::new().get(source.url());
+
+        if let Some(feed) = self.feeds.borrow().get(source.url()) {
+            if let Some(etag) = &feed.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &feed.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            println!("calendar {} not modified", source.url());
+            return Ok(());
+        }
+
+        let response = response.error_for_status()?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|e| e.to_str().ok())
+                .map(|e| e.to_string())
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        let result = response.text().await?;
+        let events = parse_events(result.as_bytes(), source.label())?;
+
+        self.feeds.borrow_mut().insert(
+            source.url().to_string(),
+            Feed {
+                etag,
+                last_modified,
+                events,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+fn parse_events(bytes: &[u8], label: Option<&str>) -> Result<Vec<Event>, anyhow::Error> {
+    let mut events: Vec<Event> = Vec::new();
+
+    let parens = Regex::new( "\\(.*\\)")?;
+    for line in ical::IcalParser::new(bytes).flatten() {
+        for event in line.events {
+            let summary = event.properties.iter().find(|e| e.name == "SUMMARY");
+            let date = event.properties.iter().find(|e| e.name == "DTSTART");
+            if let (Some(summary), Some(date)) = (summary, date) {
+                if let (Some(summary), Some(date)) = (&summary.value, &date.value) {
+                    let Some((date, time)) = parse_start(date) else {
+                        continue;
+                    };
+                    let summary = parens.replace_all(summary, "");
+
+                    if events.iter().find(|e| {
+                        e.summary == summary && e.date == date
+                    }).is_none() {
+                        events.push(Event {
+                            summary: summary.to_string(),
+                            date,
+                            time,
+                            calendar: label.map(|e| e.to_string()),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(events)
+}
+
+/// Parse a `DTSTART` value, which is either a bare date (all-day events)
+/// or a date-time, optionally in UTC when suffixed with `Z`.
+fn parse_start(value: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date_time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        let local: DateTime<Local> = DateTime::<Utc>::from_naive_utc_and_offset(date_time, Utc).into();
+        return Some((local.date_naive(), Some(local.time())));
+    }
+
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Some((date_time.date(), Some(date_time.time())));
+    }
+
+    let (date, _) = NaiveDate::parse_and_remainder(value, "%Y%m%d").ok()?;
+    Some((date, None))
 }
 
 #[derive(Debug, Clone, Hash, PartialEq)]
 pub struct Event {
     pub summary: String,
     pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    pub calendar: Option<String>,
+}
+
+/// Events on or after `from`, ordered by date with all-day events first.
+pub fn upcoming(events: &[Event], from: NaiveDate) -> Vec<&Event> {
+    let mut upcoming: Vec<_> = events.iter().filter(|e| e.date >= from).collect();
+    upcoming.sort_by_key(|e| (e.date, e.time));
+    upcoming
 }
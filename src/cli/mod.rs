@@ -7,6 +7,13 @@ use clap::{Args, Parser, Subcommand};
 use std::time::Duration;
 use chrono::{Local, Utc};
 use crate::birdnet::BirdNetClient;
+use crate::netatmo;
+use anyhow::anyhow;
+use reqwest::Url;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
 
 #[derive(Debug, Clone, Parser)]
 #[command(
@@ -26,7 +33,10 @@ pub enum Command {
     Unbox(UnboxCommand),
     Splash(SplashCommand),
     Screen(ScreenCommand),
+    Agenda(AgendaCommand),
+    Air(AirCommand),
     Loop(LoopCommand),
+    Netatmo(NetatmoCommand),
     Test(TestCommand),
 }
 
@@ -87,6 +97,40 @@ impl ScreenCommand {
     }
 }
 
+#[derive(Args, Debug, Clone)]
+#[command(about = "Draw the agenda screen", args_conflicts_with_subcommands = true)]
+pub struct AgendaCommand {}
+
+impl AgendaCommand {
+    pub async fn run<P: Paint>(&self, paint: &mut P) -> Result<(), anyhow::Error> {
+        let ds = DataSource::new();
+        let data = ds.get().await?;
+
+        let mut display = Display::new(paint);
+        display.draw_agenda_screen(&data, Utc::now())?;
+        Ok(())
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(
+about = "Draw the air quality screen",
+args_conflicts_with_subcommands = true
+)]
+pub struct AirCommand {}
+
+impl AirCommand {
+    pub async fn run<P: Paint>(&self, paint: &mut P) -> Result<(), anyhow::Error> {
+        let ds = DataSource::new();
+        let data = ds.get().await?;
+        let history = ds.get_aqi_history().await?;
+
+        let mut display = Display::new(paint);
+        display.draw_air_screen(data.now.aqi.as_ref(), &history, &data.alerts, Utc::now())?;
+        Ok(())
+    }
+}
+
 #[derive(Args, Debug, Clone)]
 #[command(about = "Loop it all", args_conflicts_with_subcommands = true)]
 pub struct LoopCommand {}
@@ -129,6 +173,131 @@ impl LoopCommand {
     }
 }
 
+#[derive(Args, Debug, Clone)]
+#[command(about = "Netatmo account setup")]
+pub struct NetatmoCommand {
+    #[command(subcommand)]
+    pub command: NetatmoSubcommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum NetatmoSubcommand {
+    Login(NetatmoLoginCommand),
+}
+
+impl NetatmoCommand {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        match &self.command {
+            NetatmoSubcommand::Login(inner) => inner.run().await,
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(about = "Authorize access to your Netatmo station and save the tokens")]
+pub struct NetatmoLoginCommand {
+    /// Loopback port that receives the redirect; must match the app's redirect URI.
+    #[arg(long, default_value_t = 8765)]
+    pub port: u16,
+
+    /// Paste the code (or the whole redirect URL) instead of listening for it.
+    #[arg(long)]
+    pub paste: bool,
+}
+
+impl NetatmoLoginCommand {
+    pub async fn run(&self) -> Result<(), anyhow::Error> {
+        let redirect_uri = format!("http://127.0.0.1:{}/callback", self.port);
+
+        let csrf_state = format!("{:016x}", RandomState::new().hash_one(Utc::now()));
+
+        let url = netatmo::authorize_url(&redirect_uri, &csrf_state)?;
+        println!("Open this URL and allow access:\n\n{}\n", url);
+
+        let redirect = if self.paste {
+            println!("Paste the code, or the URL you were redirected to:");
+            tokio::task::spawn_blocking(|| -> Result<String, anyhow::Error> {
+                let mut line = String::new();
+                std::io::stdin().read_line(&mut line)?;
+                Ok(line.trim().to_string())
+            })
+            .await??
+        } else {
+            println!("Waiting for the redirect on {} ...", redirect_uri);
+            let port = self.port;
+            tokio::task::spawn_blocking(move || receive_redirect(port)).await??
+        };
+
+        let code = if redirect.contains('?') || redirect.contains('=') {
+            // a full redirect URL, or just its query string.
+            let url = match Url::parse(&redirect) {
+                Ok(url) => url,
+                Err(_) => {
+                    let query = redirect.split_once('?').map_or(redirect.as_str(), |(_, e)| e);
+                    Url::parse(&format!("http://127.0.0.1/?{}", query))?
+                }
+            };
+            let param = |name| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.to_string())
+            };
+            if let Some(error) = param("error") {
+                return Err(anyhow!("authorization refused: {}", error));
+            }
+            if param("state").as_deref() != Some(csrf_state.as_str()) {
+                return Err(anyhow!("authorization state mismatch; try again"));
+            }
+            param("code").ok_or(anyhow!("no code in redirect"))?
+        } else {
+            redirect
+        };
+
+        netatmo::exchange_code(&code, &redirect_uri).await?;
+        println!("Netatmo authorized; refresh token saved.");
+
+        Ok(())
+    }
+}
+
+/// Waits on the loopback listener for the request to `/callback` and
+/// returns its URL. Browsers also preconnect and ask for `/favicon.ico`;
+/// anything else is answered with a 404 and skipped.
+fn receive_redirect(port: u16) -> Result<String, anyhow::Error> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+
+    loop {
+        let (stream, _) = listener.accept()?;
+
+        let mut request_line = String::new();
+        if BufReader::new(&stream).read_line(&mut request_line).is_err() {
+            continue;
+        }
+
+        let Some(path) = request_line.split_whitespace().nth(1) else {
+            continue;
+        };
+
+        if !path.starts_with("/callback") {
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+            continue;
+        }
+
+        let body = "Lattitude is authorized. You can close this window.";
+        write!(
+            &stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+
+        return Ok(format!("http://127.0.0.1:{}{}", port, path));
+    }
+}
+
 
 #[derive(Args, Debug, Clone)]
 #[command(about = "Run whatever it is you're testing", args_conflicts_with_subcommands = true)]
@@ -0,0 +1,144 @@
+use crate::calendar::Event;
+use crate::state::{CountdownState, Recurrence};
+use chrono::{Datelike, Duration, NaiveDate};
+
+const CALENDAR_LABEL: &str = "Reminders";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Countdown {
+    pub name: String,
+    pub date: NaiveDate,
+    pub days: i64,
+}
+
+impl Countdown {
+    pub fn label(&self) -> String {
+        match self.days {
+            0 => format!("{} today", self.name),
+            1 => format!("{} tomorrow", self.name),
+            days => format!("{} days until {}", days, self.name),
+        }
+    }
+}
+
+impl Recurrence {
+    /// The first occurrence on or after `from`.
+    pub fn next_occurrence(&self, from: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Date(date) => Some(*date).filter(|e| *e >= from),
+            Recurrence::Weekly(weekday) => {
+                let days = (7 + weekday.num_days_from_monday() as i64
+                    - from.weekday().num_days_from_monday() as i64)
+                    % 7;
+                Some(from + Duration::days(days))
+            }
+            Recurrence::Monthly(day) => {
+                // months without the day (e.g. the 31st) are skipped.
+                (0..12).find_map(|offset| {
+                    let month0 = from.month0() + offset;
+                    let year = from.year() + (month0 / 12) as i32;
+                    NaiveDate::from_ymd_opt(year, (month0 % 12) + 1, *day)
+                        .filter(|e| *e >= from)
+                })
+            }
+            Recurrence::Yearly(month_day) => {
+                let (month, day) = parse_month_day(month_day)?;
+                (0..8).find_map(|offset| {
+                    NaiveDate::from_ymd_opt(from.year() + offset, month, day).filter(|e| *e >= from)
+                })
+            }
+        }
+    }
+
+    /// Every occurrence in `from..=until`.
+    pub fn occurrences(&self, from: NaiveDate, until: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = Vec::new();
+        let mut cursor = from;
+        while let Some(date) = self.next_occurrence(cursor) {
+            if date > until {
+                break;
+            }
+            dates.push(date);
+            cursor = date + Duration::days(1);
+        }
+        dates
+    }
+}
+
+fn parse_month_day(month_day: &str) -> Option<(u32, u32)> {
+    let date = NaiveDate::parse_from_str(&format!("2000-{}", month_day), "%Y-%m-%d").ok()?;
+    Some((date.month(), date.day()))
+}
+
+/// The next occurrence of each countdown, soonest first.
+pub fn countdowns(countdowns: &[CountdownState], today: NaiveDate) -> Vec<Countdown> {
+    let mut countdowns: Vec<_> = countdowns
+        .iter()
+        .filter_map(|countdown| {
+            let date = countdown.when.next_occurrence(today)?;
+            Some(Countdown {
+                name: countdown.name.clone(),
+                date,
+                days: (date - today).num_days(),
+            })
+        })
+        .collect();
+
+    countdowns.sort_by_key(|e| e.days);
+    countdowns
+}
+
+/// Synthetic calendar events for every occurrence within `horizon` days.
+pub fn events(countdowns: &[CountdownState], today: NaiveDate, horizon: i64) -> Vec<Event> {
+    let until = today + Duration::days(horizon);
+
+    countdowns
+        .iter()
+        .flat_map(|countdown| {
+            countdown
+                .when
+                .occurrences(today, until)
+                .into_iter()
+                .map(|date| Event {
+                    summary: countdown.name.clone(),
+                    date,
+                    time: None,
+                    calendar: Some(CALENDAR_LABEL.to_string()),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::state::Recurrence;
+    use chrono::{NaiveDate, Weekday};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn weekly() {
+        // 2026-10-19 is a Monday.
+        let trash = Recurrence::Weekly(Weekday::Tue);
+        assert_eq!(trash.next_occurrence(date(2026, 10, 19)), Some(date(2026, 10, 20)));
+        assert_eq!(trash.next_occurrence(date(2026, 10, 20)), Some(date(2026, 10, 20)));
+        assert_eq!(
+            trash.occurrences(date(2026, 10, 19), date(2026, 11, 3)),
+            vec![date(2026, 10, 20), date(2026, 10, 27), date(2026, 11, 3)]
+        );
+    }
+
+    #[test]
+    fn monthly_and_yearly() {
+        let rent = Recurrence::Monthly(31);
+        assert_eq!(rent.next_occurrence(date(2026, 11, 2)), Some(date(2026, 12, 31)));
+
+        let christmas = Recurrence::Yearly("12-25".to_string());
+        assert_eq!(christmas.next_occurrence(date(2026, 12, 26)), Some(date(2027, 12, 25)));
+
+        let vacation = Recurrence::Date(date(2026, 12, 20));
+        assert_eq!(vacation.next_occurrence(date(2026, 12, 21)), None);
+    }
+}
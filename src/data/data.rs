@@ -1,7 +1,7 @@
-use crate::accuweather::daily_forecast::DailyForecast;
-use crate::accuweather::hourly_forecast::HourlyForecast;
 use crate::calendar::Event;
-use crate::netatmo::{Humidity, Pressure, Rain, Temperature, Wind};
+use crate::countdown::Countdown;
+use crate::forecast::{Alert, DailyForecast, HourlyForecast};
+use crate::netatmo::{Co2, History, Humidity, Noise, Pressure, Rain, Temperature, Wind};
 use crate::purple::Aqi;
 use chrono::{DateTime, Utc};
 
@@ -12,7 +12,12 @@ pub struct DisplayData {
     pub daily_forecast: Vec<DailyForecast>,
     pub hourly_forecast: Vec<HourlyForecast>,
     pub events: Vec<Event>,
+    pub countdowns: Vec<Countdown>,
     pub birds: Vec<String>,
+    /// Active watches and warnings, most severe first.
+    pub alerts: Vec<Alert>,
+    /// Problems worth a glance, such as an offline Netatmo module.
+    pub notices: Vec<String>,
 }
 
 #[derive(PartialEq)]
@@ -23,4 +28,15 @@ pub struct NowData {
     pub wind: Option<Wind>,
     pub rain: Option<Rain>,
     pub aqi: Option<Aqi>,
+    pub storm_incoming: bool,
+    pub history: Option<History>,
+    pub inside: InsideData,
+}
+
+#[derive(PartialEq, Default)]
+pub struct InsideData {
+    pub temp: Option<Temperature>,
+    pub humidity: Option<Humidity>,
+    pub co2: Option<Co2>,
+    pub noise: Option<Noise>,
 }
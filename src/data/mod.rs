@@ -1,15 +1,18 @@
-use crate::accuweather::daily_forecast::DailyForecast;
-use crate::accuweather::hourly_forecast::HourlyForecast;
 use crate::calendar::Event;
-use crate::data::data::{DisplayData, NowData};
-use crate::netatmo::{NetatmoData, };
-use crate::purple::Aqi;
-use crate::{accuweather, calendar, netatmo, purple};
-use chrono::{DateTime, Duration, Utc};
+use crate::data::data::{DisplayData, InsideData, NowData};
+use crate::forecast::{Alert, DailyForecast, ForecastClient, HourlyForecast};
+use crate::netatmo::{storm_incoming, History, Measurement, NetatmoData, NetatmoError, Pressure, TokenManager};
+use crate::nws::NwsClient;
+use crate::purple::{Aqi, HistoryReading};
+use crate::{calendar, countdown, purple};
+use crate::state::state;
+use chrono::{DateTime, Duration, Local, Utc};
 use std::cell::{RefCell};
 use std::future::Future;
 use std::pin::Pin;
+use std::rc::Rc;
 use crate::birdnet::BirdNetClient;
+use crate::birthdays::BirthdayClient;
 
 #[allow(clippy::module_inception)]
 pub mod data;
@@ -46,12 +49,22 @@ impl<T: Clone> CachedData<T> {
     }
 }
 
+const PRESSURE_HISTORY_HOURS: i64 = 6;
+
+/// How far ahead countdowns are expanded into calendar events.
+const COUNTDOWN_HORIZON_DAYS: i64 = 60;
+
 pub struct DataSource {
     calendar: CachedData<Vec<Event>>,
+    birthdays: CachedData<Vec<Event>>,
     netatmo: CachedData<NetatmoData>,
+    netatmo_tokens: Rc<TokenManager>,
+    pressure_history: RefCell<Vec<Measurement>>,
     purple: CachedData<Aqi>,
-    accuweather_daily: CachedData<Vec<DailyForecast>>,
-    accuweather_hourly: CachedData<Vec<HourlyForecast>>,
+    purple_history: CachedData<Vec<HistoryReading>>,
+    daily_forecast: CachedData<Vec<DailyForecast>>,
+    hourly_forecast: CachedData<Vec<HourlyForecast>>,
+    alerts: CachedData<Vec<Alert>>,
     birdnet: CachedData<Vec<String>>,
 }
 
@@ -69,13 +82,27 @@ fn fetch_birdnet() -> Pin<Box<dyn Future<Output = Result<Vec<String>, anyhow::Er
 }
 
 fn calendar_cadence() -> Duration {
-    Duration::days(1)
+    Duration::minutes(15)
 }
 
-fn fetch_calendar() -> Pin<Box<dyn Future<Output = Result<Vec<Event>, anyhow::Error>>>> {
+fn fetch_calendar(
+    client: Rc<calendar::CalendarClient>,
+) -> Pin<Box<dyn Future<Output = Result<Vec<Event>, anyhow::Error>>>> {
     Box::pin(async move {
         println!("fetch calendar");
-        let client = calendar::CalendarClient::new();
+        let events = client.events().await?;
+        Ok(events)
+    })
+}
+
+fn birthdays_cadence() -> Duration {
+    Duration::hours(1)
+}
+
+fn fetch_birthdays() -> Pin<Box<dyn Future<Output = Result<Vec<Event>, anyhow::Error>>>> {
+    Box::pin(async move {
+        println!("fetch birthdays");
+        let client = BirthdayClient::new();
         let events = client.events().await?;
         Ok(events)
     })
@@ -85,54 +112,98 @@ fn netatmo_cadence() -> Duration {
     Duration::minutes(15)
 }
 
-fn fetch_netatmo() -> Pin<Box<dyn Future<Output = Result<NetatmoData, anyhow::Error>>>> {
+fn fetch_netatmo(
+    tokens: Rc<TokenManager>,
+) -> Pin<Box<dyn Future<Output = Result<NetatmoData, anyhow::Error>>>> {
     Box::pin(async move {
         println!("fetch netatmo");
-        let netatmo_client = netatmo::get_client().await?;
-        let netatmo_data = netatmo_client.get_station_data().await?;
+        let netatmo_client = tokens.client().await?;
+        let mut netatmo_data = match netatmo_client.get_station_data().await {
+            Ok(netatmo_data) => netatmo_data,
+            Err(err) => {
+                if TokenManager::token_rejected(&err) {
+                    tokens.invalidate();
+                }
+                return Err(err);
+            }
+        };
+
+        match netatmo_client.get_history(&netatmo_data).await {
+            Ok(history) => netatmo_data.history = Some(history),
+            Err(err) => println!("netatmo history: {}", err),
+        }
+
         Ok(netatmo_data)
     })
 }
 
-fn purple_cadence() -> Duration {
-    Duration::hours(2)
-}
-
-fn fetch_purple() -> Pin<Box<dyn Future<Output = Result<Aqi, anyhow::Error>>>> {
+fn fetch_purple(
+    purple_client: Rc<purple::PurpleClient>,
+) -> Pin<Box<dyn Future<Output = Result<Aqi, anyhow::Error>>>> {
     Box::pin(async move {
         println!("fetch purple");
-        let purple_client = purple::PurpleClient::new();
         let aqi = purple_client.get_aqi().await?;
         Ok(aqi)
     })
 }
 
-pub fn accuweather_cadence() -> Duration {
-    Duration::minutes(30)
+fn purple_history_cadence() -> Duration {
+    Duration::hours(1)
+}
+
+fn fetch_purple_history(
+    purple_client: Rc<purple::PurpleClient>,
+) -> Pin<Box<dyn Future<Output = Result<Vec<HistoryReading>, anyhow::Error>>>> {
+    Box::pin(async move {
+        println!("fetch purple history");
+        let history = purple_client.get_history().await?;
+        Ok(history)
+    })
 }
 
-fn fetch_accuweather_daily_forecast(
+fn fetch_daily_forecast(
+    client: Rc<ForecastClient>,
 ) -> Pin<Box<dyn Future<Output = Result<Vec<DailyForecast>, anyhow::Error>>>> {
     Box::pin(async move {
-        println!("fetch accuweather");
-        let client = accuweather::AccuWeatherClient::new();
+        println!("fetch daily forecast");
         let forecast = client.daily_forecast().await?;
         Ok(forecast)
     })
 }
 
-fn fetch_accuweather_hourly_forecast(
+fn fetch_hourly_forecast(
+    client: Rc<ForecastClient>,
 ) -> Pin<Box<dyn Future<Output = Result<Vec<HourlyForecast>, anyhow::Error>>>> {
     Box::pin(async move {
-        println!("fetch accuweather hourly");
-        let client = accuweather::AccuWeatherClient::new();
+        println!("fetch hourly forecast");
         let forecast = client.hourly_forecasts().await?;
         Ok(forecast)
     })
 }
 
+fn alerts_cadence() -> Duration {
+    Duration::minutes(5)
+}
+
+fn fetch_alerts() -> Pin<Box<dyn Future<Output = Result<Vec<Alert>, anyhow::Error>>>> {
+    Box::pin(async move {
+        if !state().forecast.alerts_enabled() {
+            return Ok(vec![]);
+        }
+        println!("fetch alerts");
+        let client = NwsClient::new();
+        let alerts = client.alerts().await?;
+        Ok(alerts)
+    })
+}
+
 impl DataSource {
     pub fn new() -> Self {
+        let calendar_client = Rc::new(calendar::CalendarClient::new());
+        let netatmo_tokens = Rc::new(TokenManager::new());
+        let purple_client = Rc::new(purple::PurpleClient::new());
+        let forecast_client = Rc::new(ForecastClient::new());
+
         Self {
             birdnet: CachedData {
                 data: RefCell::new(None),
@@ -143,32 +214,70 @@ impl DataSource {
             calendar: CachedData {
                 data: RefCell::new(None),
                 as_of: RefCell::new(None),
-                fetch: Box::new(fetch_calendar),
+                fetch: Box::new(move || fetch_calendar(calendar_client.clone())),
                 cadence: Box::new(calendar_cadence),
             },
+            birthdays: CachedData {
+                data: RefCell::new(None),
+                as_of: RefCell::new(None),
+                fetch: Box::new(fetch_birthdays),
+                cadence: Box::new(birthdays_cadence),
+            },
             netatmo: CachedData {
                 data: RefCell::new(None),
                 as_of: RefCell::new(None),
-                fetch: Box::new(fetch_netatmo),
+                fetch: Box::new({
+                    let netatmo_tokens = netatmo_tokens.clone();
+                    move || fetch_netatmo(netatmo_tokens.clone())
+                }),
                 cadence: Box::new(netatmo_cadence),
             },
+            netatmo_tokens,
+            pressure_history: RefCell::new(vec![]),
             purple: CachedData {
                 data: RefCell::new(None),
                 as_of: RefCell::new(None),
-                fetch: Box::new(fetch_purple),
-                cadence: Box::new(purple_cadence),
+                fetch: Box::new({
+                    let purple_client = purple_client.clone();
+                    move || fetch_purple(purple_client.clone())
+                }),
+                cadence: Box::new({
+                    let purple_client = purple_client.clone();
+                    move || purple_client.cadence()
+                }),
+            },
+            purple_history: CachedData {
+                data: RefCell::new(None),
+                as_of: RefCell::new(None),
+                fetch: Box::new(move || fetch_purple_history(purple_client.clone())),
+                cadence: Box::new(purple_history_cadence),
+            },
+            daily_forecast: CachedData {
+                data: RefCell::new(None),
+                as_of: RefCell::new(None),
+                fetch: Box::new({
+                    let forecast_client = forecast_client.clone();
+                    move || fetch_daily_forecast(forecast_client.clone())
+                }),
+                cadence: Box::new({
+                    let forecast_client = forecast_client.clone();
+                    move || forecast_client.cadence()
+                }),
             },
-            accuweather_daily: CachedData {
+            hourly_forecast: CachedData {
                 data: RefCell::new(None),
                 as_of: RefCell::new(None),
-                fetch: Box::new(fetch_accuweather_daily_forecast),
-                cadence: Box::new(accuweather_cadence),
+                fetch: Box::new({
+                    let forecast_client = forecast_client.clone();
+                    move || fetch_hourly_forecast(forecast_client.clone())
+                }),
+                cadence: Box::new(move || forecast_client.cadence()),
             },
-            accuweather_hourly: CachedData {
+            alerts: CachedData {
                 data: RefCell::new(None),
                 as_of: RefCell::new(None),
-                fetch: Box::new(fetch_accuweather_hourly_forecast),
-                cadence: Box::new(accuweather_cadence),
+                fetch: Box::new(fetch_alerts),
+                cadence: Box::new(alerts_cadence),
             },
         }
     }
@@ -179,25 +288,85 @@ impl DataSource {
             now: self.get_now().await?,
             daily_forecast: self.get_daily_forecast().await?,
             hourly_forecast: self.get_hourly_forecast().await?,
-            events: self.calendar.get().await?.unwrap_or(vec![]),
+            events: self.get_events().await?,
+            countdowns: countdown::countdowns(&state().countdowns, Local::now().date_naive()),
             birds: self.birdnet.get().await?.unwrap_or(vec![]),
+            alerts: self.get_alerts().await,
+            notices: self.get_notices(),
         })
     }
 
-    async fn get_daily_forecast(&self) -> Result<Vec<DailyForecast>, anyhow::Error> {
-        if let Some(forecast) = self.accuweather_daily.get().await? {
-            Ok(forecast)
-        } else {
-            Ok(vec![])
+    /// Hourly air quality for the last day. Only the air screen needs it,
+    /// so it is not part of [`DisplayData`].
+    pub async fn get_aqi_history(&self) -> Result<Vec<HistoryReading>, anyhow::Error> {
+        Ok(self.purple_history.get().await?.unwrap_or(vec![]))
+    }
+
+    /// Alerts are only refreshed every few minutes, so ones that have run
+    /// out since are dropped here.
+    async fn get_alerts(&self) -> Vec<Alert> {
+        let alerts = match self.alerts.get().await {
+            Ok(alerts) => alerts.unwrap_or_default(),
+            Err(err) => {
+                println!("alerts: {}", err);
+                self.alerts.data.borrow().clone().unwrap_or_default()
+            }
+        };
+
+        let now = Local::now();
+        alerts
+            .into_iter()
+            .filter(|e| e.expires.map(|expires| expires > now).unwrap_or(true))
+            .collect()
+    }
+
+    fn get_notices(&self) -> Vec<String> {
+        let mut notices = vec![];
+
+        if self.netatmo_tokens.reauthorization_required() {
+            notices.push(NetatmoError::ReauthorizationRequired.to_string());
+        }
+
+        if let Some(netatmo) = &*self.netatmo.data.borrow() {
+            notices.extend(netatmo.module_alerts());
         }
+
+        notices
+    }
+
+    async fn get_events(&self) -> Result<Vec<Event>, anyhow::Error> {
+        let mut events = self.calendar.get().await?.unwrap_or(vec![]);
+        events.extend(self.birthdays.get().await?.unwrap_or(vec![]));
+        events.extend(countdown::events(
+            &state().countdowns,
+            Local::now().date_naive(),
+            COUNTDOWN_HORIZON_DAYS,
+        ));
+        Ok(events)
+    }
+
+    /// A failed refresh keeps showing the last forecast rather than
+    /// stopping the loop; the client backs off on its own.
+    async fn get_daily_forecast(&self) -> Result<Vec<DailyForecast>, anyhow::Error> {
+        let forecast = match self.daily_forecast.get().await {
+            Ok(forecast) => forecast,
+            Err(err) => {
+                println!("daily forecast: {}", err);
+                self.daily_forecast.data.borrow().clone()
+            }
+        };
+        Ok(forecast.unwrap_or(vec![]))
     }
 
     async fn get_hourly_forecast(&self) -> Result<Vec<HourlyForecast>, anyhow::Error> {
-        if let Some(forecast) = self.accuweather_hourly.get().await? {
-            Ok(forecast)
-        } else {
-            Ok(vec![])
-        }
+        let forecast = match self.hourly_forecast.get().await {
+            Ok(forecast) => forecast,
+            Err(err) => {
+                println!("hourly forecast: {}", err);
+                self.hourly_forecast.data.borrow().clone()
+            }
+        };
+        Ok(forecast.unwrap_or(vec![]))
     }
 
     async fn get_now(&self) -> Result<NowData, anyhow::Error> {
@@ -208,14 +377,34 @@ impl DataSource {
             wind: None,
             rain: None,
             aqi: None,
+            storm_incoming: false,
+            history: None,
+            inside: InsideData::default(),
+        };
+
+        let netatmo = match self.netatmo.get().await {
+            Ok(netatmo) => netatmo,
+            Err(err) => {
+                println!("netatmo: {}", err);
+                self.netatmo.data.borrow().clone()
+            }
         };
 
-        if let Some(netatmo) = self.netatmo.get().await? {
+        if let Some(netatmo) = netatmo {
             now_data.temp = netatmo.outside_temp();
             now_data.wind = netatmo.wind();
             now_data.rain = netatmo.rain();
             now_data.humidity = netatmo.humidity();
             now_data.pressure = netatmo.pressure();
+            now_data.storm_incoming =
+                self.record_pressure(now_data.pressure, netatmo.history.as_ref());
+            now_data.history = netatmo.history.clone();
+            now_data.inside = InsideData {
+                temp: netatmo.inside_temp(),
+                humidity: netatmo.inside_humidity(),
+                co2: netatmo.co2(),
+                noise: netatmo.noise(),
+            };
         }
 
         if let Ok(purple) = self.purple.get().await {
@@ -224,4 +413,30 @@ impl DataSource {
 
         Ok(now_data)
     }
+
+    /// Keeps a few hours of pressure readings, one per Netatmo fetch, and
+    /// reports whether they point to a storm. Netatmo's own history is
+    /// preferred when it could be fetched.
+    fn record_pressure(&self, pressure: Option<Pressure>, netatmo_history: Option<&History>) -> bool {
+        let mut history = self.pressure_history.borrow_mut();
+
+        if let (Some(pressure), Some(as_of)) = (pressure, *self.netatmo.as_of.borrow()) {
+            if history.last().map(|e| e.time) != Some(as_of) {
+                history.push(Measurement {
+                    time: as_of,
+                    value: pressure.pressure,
+                });
+            }
+        }
+
+        let now = Utc::now();
+        history.retain(|e| now - e.time < Duration::hours(PRESSURE_HISTORY_HOURS));
+
+        match netatmo_history {
+            Some(netatmo_history) if !netatmo_history.pressure.is_empty() => {
+                storm_incoming(&netatmo_history.pressure, now)
+            }
+            _ => storm_incoming(&history, now),
+        }
+    }
 }
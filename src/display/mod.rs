@@ -1,21 +1,35 @@
-use crate::accuweather::daily_forecast::DailyForecast;
-use crate::accuweather::hourly_forecast::HourlyForecast;
+use crate::aqi::Category;
+use crate::astronomy::MoonPhase;
 use crate::art::{aqi, arrow_down, arrow_level, arrow_small_down, arrow_small_up, arrow_up, logo, moon_first_quarter, moon_full, moon_new, moon_third_quarter, moon_waning_crescent, moon_waning_gibbous, moon_waxing_crescent, moon_waxing_gibbous, usb, weather, wind};
-use crate::calendar::Event;
-use crate::data::data::{DisplayData, NowData};
+use crate::calendar::{upcoming, Event};
+use crate::countdown::Countdown;
+use crate::data::data::{DisplayData, InsideData, NowData};
+use crate::forecast::{Alert, Condition, DailyForecast, HourlyForecast, Severity};
 use crate::font::{sanserif, sanserif_bold, typewriter, typewriter_bold};
-use crate::graphics::{lighten_bmp, rotate_bmp, trim_bmp, Color, Darkness, Graphics, Thickness, ViewPort, scale_bmp};
-use crate::netatmo::Trend;
+use crate::graphics::{lighten_bmp, overlay_bmp, rotate_bmp, trim_bmp, Color, Darkness, Graphics, Thickness, ViewPort, scale_bmp};
+use crate::netatmo::{Co2Level, Measurement, Pressure, Rain, Trend};
 use crate::paint::Paint;
+use crate::purple::{Aqi, HistoryReading};
 use bmp::Image;
-use chrono::{DateTime, Datelike, Local, Timelike, Weekday, Utc};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, Timelike, Weekday, Utc};
 use glyph_brush_layout::{HorizontalAlign, VerticalAlign};
 use std::env;
 use crate::graphics::Darkness::Dark;
+use crate::state::{state, DailyDetail, HourlyStyle};
 
 pub const WIDTH: usize = 1404;
 pub const HEIGHT: usize = 1872;
 
+const DAILY_FORECAST_TOP: usize = 640;
+const DAILY_FORECAST_ROWS: usize = 5;
+const DAILY_FORECAST_ROW_HEIGHT: usize = 250;
+const AGENDA_ROW_HEIGHT: usize = 44;
+const NOTICES_TOP: usize = HEIGHT - 34;
+/// Where countdowns start on the agenda screen, below its title.
+const AGENDA_COUNTDOWNS_TOP: usize = 1200;
+const AQI_CHART_GRID: u16 = 50;
+const HOURLY_COLUMNS: usize = 12;
+
 pub struct Display<'p, P: Paint> {
     graphics: Graphics<WIDTH, HEIGHT>,
     paint: &'p mut P,
@@ -136,19 +150,241 @@ impl<'p, P: Paint> Display<'p, P> {
         let viewport = self.graphics.viewport((10, 20), (1400, 300));
         self.current(viewport, &data.now, &data.birds)?;
 
+        let viewport = self.graphics.viewport((840, 350), (500, 76));
+        self.inside(viewport, &data.now.inside)?;
+
         let viewport = self.graphics.default_viewport().shift_down(430);
 
-        self.hourly_forecast(viewport, &data.hourly_forecast)?;
+        self.hourly_forecast(viewport, &data.hourly_forecast, &data.daily_forecast)?;
 
         self.daily_forecast(&data)?;
 
-        self.header(time)?;
+        self.notices(&data.notices)?;
+
+        if data.alerts.is_empty() {
+            self.header(time)?;
+
+            if let Some(countdown) = data.countdowns.first() {
+                let countdown_vp = self.graphics.viewport(((WIDTH - 560) / 2, 34), (560, 40));
+                self.countdown(countdown_vp, countdown)?;
+            }
+        } else {
+            self.alerts(&data.alerts, time)?;
+        }
+
+        self.paint()?;
+
+        Ok(())
+    }
+
+    pub fn draw_agenda_screen(&mut self, data: &DisplayData, time: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        let today = DateTime::<Local>::from(time).date_naive();
+
+        let viewport = self.graphics.default_viewport().padded_left(30).padded_right(30).shift_down(110);
+        viewport.text(
+            "Agenda",
+            56.0,
+            &typewriter_bold()?,
+            HorizontalAlign::Left,
+            VerticalAlign::Top,
+            Darkness::Dark,
+        );
+        viewport.shift_down(80).hline((0, 0), viewport.width(), Thickness::Medium, Color::Gray8);
+
+        // however many events are configured, they stop above the countdowns.
+        self.agenda(
+            viewport.viewport((0, 110), (viewport.width(), AGENDA_COUNTDOWNS_TOP - 110)),
+            &data.events,
+            today,
+            today,
+            state().display.agenda_events,
+        )?;
+
+        let mut countdown_vp = viewport.shift_down(AGENDA_COUNTDOWNS_TOP);
+        for countdown in data.countdowns.iter().take(8) {
+            self.countdown(countdown_vp, countdown)?;
+            countdown_vp = countdown_vp.shift_down(AGENDA_ROW_HEIGHT);
+        }
+
+        if data.alerts.is_empty() {
+            self.header(time)?;
+        } else {
+            self.alerts(&data.alerts, time)?;
+        }
+
+        self.paint()?;
+
+        Ok(())
+    }
+
+    pub fn draw_air_screen(
+        &mut self,
+        aqi: Option<&Aqi>,
+        history: &[HistoryReading],
+        alerts: &[Alert],
+        time: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        let viewport = self.graphics.default_viewport().padded_left(30).padded_right(30).shift_down(110);
+        viewport.text(
+            "Air Quality",
+            56.0,
+            &typewriter_bold()?,
+            HorizontalAlign::Left,
+            VerticalAlign::Top,
+            Darkness::Dark,
+        );
+        viewport.shift_down(80).hline((0, 0), viewport.width(), Thickness::Medium, Color::Gray8);
+
+        if let Some(aqi) = aqi {
+            let index = aqi.index();
+            let now_vp = viewport.shift_down(130);
+
+            let rect = now_vp.text(
+                &format!("{}", index.value),
+                150.0,
+                &typewriter()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+
+            let details_vp = now_vp.shift_right(rect.max.x as usize - now_vp.x_offset + 40);
+            details_vp.text(
+                index.category.label(),
+                44.0,
+                &typewriter_bold()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+
+            let trend = match aqi.trend() {
+                Trend::Up => "rising",
+                Trend::Down => "falling",
+                Trend::Stable => "steady",
+            };
+            details_vp.shift_down(60).text(
+                &format!(
+                    "{}, from {} sensor{}",
+                    trend,
+                    aqi.sensors,
+                    if aqi.sensors == 1 { "" } else { "s" }
+                ),
+                32.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Medium,
+            );
+
+            details_vp.shift_down(105).text(
+                &format!(
+                    "PM2.5 µg/m³   1h {:.1}   6h {:.1}   24h {:.1}",
+                    aqi.one_hour, aqi.six_hour, aqi.twenty_four_hour
+                ),
+                28.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Medium,
+            );
+        }
+
+        let chart_vp = viewport.shift_down(450);
+        chart_vp.text(
+            "Last 24 hours",
+            36.0,
+            &typewriter_bold()?,
+            HorizontalAlign::Left,
+            VerticalAlign::Top,
+            Darkness::Dark,
+        );
+        self.aqi_chart(chart_vp.shift_down(80).viewport((0, 0), (chart_vp.width(), 700)), history)?;
+
+        if alerts.is_empty() {
+            self.header(time)?;
+        } else {
+            self.alerts(alerts, time)?;
+        }
 
         self.paint()?;
 
         Ok(())
     }
 
+    /// One bar per hourly reading, with gridlines at each category boundary.
+    fn aqi_chart<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        history: &[HistoryReading],
+    ) -> Result<(), anyhow::Error> {
+        if history.is_empty() {
+            viewport.text(
+                "no history",
+                32.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Light,
+            );
+            return Ok(());
+        }
+
+        let indexes: Vec<_> = history.iter().map(|e| e.index()).collect();
+
+        // keep at least the Moderate band visible, rounded up to a gridline.
+        let max = indexes.iter().map(|e| e.value).max().unwrap_or_default().max(100);
+        let scale = max.div_ceil(AQI_CHART_GRID) * AQI_CHART_GRID;
+
+        let axis_width = 80;
+        let label_height = 50;
+        let plot_vp = viewport.padded_left(axis_width);
+        let plot_height = plot_vp.height() - label_height;
+
+        let y_for = |value: u16| plot_height - (value as usize * plot_height / scale as usize);
+
+        for gridline in (0..=scale).step_by(AQI_CHART_GRID as usize) {
+            let y = y_for(gridline);
+            plot_vp.hline((0, y), plot_vp.width(), Thickness::Thin, Color::Gray12);
+            viewport.viewport((0, y.saturating_sub(14)), (axis_width - 10, 30)).text(
+                &format!("{}", gridline),
+                22.0,
+                &sanserif()?,
+                HorizontalAlign::Right,
+                VerticalAlign::Top,
+                Darkness::Medium,
+            );
+        }
+
+        let slot = plot_vp.width() / history.len();
+        let bar_width = slot.saturating_sub(6).max(1);
+
+        for (i, (reading, index)) in history.iter().zip(&indexes).enumerate() {
+            let top = y_for(index.value);
+            let color = match index.category {
+                Category::Good => Color::Gray10,
+                Category::Moderate => Color::Gray7,
+                Category::UnhealthyForSensitiveGroups => Color::Gray4,
+                _ => Color::Black,
+            };
+            plot_vp.fill_rect((i * slot + 3, top), (bar_width, plot_height - top), color);
+
+            let local = DateTime::<Local>::from(reading.time);
+            if local.hour() % 6 == 0 {
+                plot_vp.viewport((i * slot, plot_height + 10), (120, 30)).text(
+                    &human_time(local),
+                    22.0,
+                    &sanserif()?,
+                    HorizontalAlign::Left,
+                    VerticalAlign::Top,
+                    Darkness::Medium,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     fn current<const WIDTH: usize, const HEIGHT: usize>(
         &self,
         viewport: ViewPort<'_, WIDTH, HEIGHT>,
@@ -173,33 +409,22 @@ impl<'p, P: Paint> Display<'p, P> {
                     (300, (rect.max.y - rect.min.y) as usize - 30),
                 );
 
-                match &temp.temp_trend {
-                    Some(Trend::Up) => {
-                        trend_vp.bmp(
-                            &trim_bmp(&arrow_up()?),
-                            HorizontalAlign::Left,
-                            VerticalAlign::Center,
-                        );
-                    }
-                    Some(Trend::Down) => {
-                        trend_vp.bmp(
-                            &trim_bmp(&arrow_down()?),
-                            HorizontalAlign::Left,
-                            VerticalAlign::Center,
-                        );
-                    }
-                    Some(Trend::Stable) => {
-                        trend_vp.bmp(
-                            &trim_bmp(&arrow_level()?),
-                            HorizontalAlign::Left,
-                            VerticalAlign::Center,
-                        );
-                    }
-                    None => { /* nothing */ }
+                if let Some(trend) = &temp.temp_trend {
+                    trend_vp.bmp(
+                        &trim_bmp(&trend_icon(trend)?),
+                        HorizontalAlign::Left,
+                        VerticalAlign::Center,
+                    );
                 }
             }
         }
 
+        if let Some(history) = &data.history {
+            let sparkline_vp = viewport.viewport((220, 252), (320, 40));
+            sparkline(sparkline_vp, &history.humidity, Color::Gray11);
+            sparkline(sparkline_vp, &history.temperature, Color::Black);
+        }
+
         if let Some(aqi_data) = &data.aqi {
             let aqi_vp = viewport.viewport((0, 170), (200, 300));
 
@@ -212,14 +437,41 @@ impl<'p, P: Paint> Display<'p, P> {
                 VerticalAlign::Center,
             );
 
+            let index = aqi_data.index();
+
             aqi_vp.text(
-                &format!("{:.0}", aqi_data.current),
+                &format!("{}", index.value),
                 80.0,
                 &typewriter()?,
                 HorizontalAlign::Center,
                 VerticalAlign::Center,
                 Darkness::Dark,
             );
+
+            let trend_icon = match aqi_data.trend() {
+                Trend::Up => Some(arrow_small_up()?),
+                Trend::Down => Some(arrow_small_down()?),
+                Trend::Stable => None,
+            };
+            if let Some(icon) = trend_icon {
+                aqi_vp.viewport((180, 0), (40, aqi_vp.height())).bmp(
+                    &trim_bmp(&icon),
+                    HorizontalAlign::Left,
+                    VerticalAlign::Center,
+                );
+            }
+
+            viewport.viewport((0, 350), (220, 60)).text(
+                index.category.label(),
+                22.0,
+                &typewriter_bold()?,
+                HorizontalAlign::Center,
+                VerticalAlign::Top,
+                match index.category {
+                    Category::Good | Category::Moderate => Darkness::Medium,
+                    _ => Darkness::Dark,
+                },
+            );
         }
 
         if let Some(wind) = &data.wind {
@@ -269,6 +521,14 @@ impl<'p, P: Paint> Display<'p, P> {
             );
         }
 
+        if let Some(pressure) = &data.pressure {
+            self.pressure(viewport.viewport((830, 50), (500, 44)), pressure, data.storm_incoming)?;
+        }
+
+        if let Some(rain) = &data.rain {
+            self.rain(viewport.viewport((220, 300), (330, 100)), rain)?;
+        }
+
         let mut bird_vp = viewport.viewport((830, 100), (500, 300));
 
         for bird in birds.iter().take(6) {
@@ -286,18 +546,344 @@ impl<'p, P: Paint> Display<'p, P> {
         Ok(())
     }
 
+    fn pressure<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        pressure: &Pressure,
+        storm_incoming: bool,
+    ) -> Result<(), anyhow::Error> {
+        let rect = viewport.text(
+            &format!("{:.0} mb", pressure.pressure),
+            30.0,
+            &typewriter_bold()?,
+            HorizontalAlign::Right,
+            VerticalAlign::Center,
+            Darkness::Dark,
+        );
+        let rect = viewport.relative(rect);
+
+        let mut left = rect.min.x as usize;
+
+        if let Some(trend) = &pressure.pressure_trend {
+            let icon = scale_bmp(&trim_bmp(&trend_icon(trend)?), 0.3);
+            left = left.saturating_sub(icon.get_width() as usize + 12);
+            viewport
+                .viewport((left, 0), (icon.get_width() as usize, viewport.height()))
+                .bmp(&icon, HorizontalAlign::Left, VerticalAlign::Center);
+        }
+
+        if storm_incoming {
+            let padding = (viewport.width() - left + 20).min(viewport.width());
+            viewport.padded_right(padding).text(
+                "storm incoming",
+                28.0,
+                &typewriter_bold()?,
+                HorizontalAlign::Right,
+                VerticalAlign::Center,
+                Darkness::Dark,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn rain<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        rain: &Rain,
+    ) -> Result<(), anyhow::Error> {
+        let units = state().display.rain_units;
+
+        if rain.is_raining() {
+            viewport.text(
+                "raining now",
+                28.0,
+                &typewriter_bold()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+        }
+
+        viewport.shift_down(36).text(
+            &format!("{} last hour", units.format(rain.sum_rain_1)),
+            24.0,
+            &typewriter()?,
+            HorizontalAlign::Left,
+            VerticalAlign::Top,
+            Darkness::Dark,
+        );
+
+        viewport.shift_down(66).text(
+            &format!("{} last 24h", units.format(rain.sum_rain_24)),
+            24.0,
+            &typewriter()?,
+            HorizontalAlign::Left,
+            VerticalAlign::Top,
+            Darkness::Light,
+        );
+
+        Ok(())
+    }
+
+    fn inside<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        data: &InsideData,
+    ) -> Result<(), anyhow::Error> {
+        let mut conditions = vec![];
+        if let Some(temperature) = data.temp.as_ref().and_then(|e| e.temperature) {
+            conditions.push(format!("{:.1}°", c_to_f(temperature as f64)));
+        }
+        if let Some(humidity) = &data.humidity {
+            conditions.push(format!("{:.0}%", humidity.humidity));
+        }
+
+        if !conditions.is_empty() {
+            viewport.text(
+                &format!("inside {}", conditions.join("  ")),
+                28.0,
+                &typewriter_bold()?,
+                HorizontalAlign::Right,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+        }
+
+        let mut air = vec![];
+        if let Some(co2) = &data.co2 {
+            air.push(format!("CO2 {} ppm {}", co2.co2, co2.level().label()));
+        }
+        if let Some(noise) = &data.noise {
+            air.push(format!("{} dB", noise.noise));
+        }
+
+        if !air.is_empty() {
+            let darkness = match data.co2.map(|e| e.level()) {
+                Some(Co2Level::Bad) => Darkness::Dark,
+                Some(Co2Level::Ventilate) => Darkness::Medium,
+                _ => Darkness::Light,
+            };
+            viewport.shift_down(36).text(
+                &air.join(" • "),
+                24.0,
+                &typewriter()?,
+                HorizontalAlign::Right,
+                VerticalAlign::Top,
+                darkness,
+            );
+        }
+
+        Ok(())
+    }
+
     fn daily_forecast(&self, data: &DisplayData) -> Result<(), anyhow::Error> {
-        let mut viewport = self.graphics.default_viewport().shift_down(640);
+        let mut viewport = self.graphics.default_viewport().shift_down(DAILY_FORECAST_TOP);
 
-        for (i, forecast) in data.daily_forecast.iter().enumerate() {
+        for (i, forecast) in data.daily_forecast.iter().take(DAILY_FORECAST_ROWS).enumerate() {
             if i != 0 {
-                viewport = viewport.shift_down(220);
+                viewport = viewport.shift_down(DAILY_FORECAST_ROW_HEIGHT - 30);
                 viewport.hline((10, 0), WIDTH - 20, Thickness::Medium, Color::Gray13);
                 viewport.hline((180, 0), WIDTH - 360, Thickness::Medium, Color::Gray8);
                 viewport = viewport.shift_down(30);
             }
             self.day_forecast(viewport, forecast, &data.events)?;
         }
+
+        // when the forecast doesn't fill every row, list the events beyond it instead.
+        let rows = data.daily_forecast.len().min(DAILY_FORECAST_ROWS);
+        if rows < DAILY_FORECAST_ROWS {
+            let today = Local::now().date_naive();
+            let from = data
+                .daily_forecast
+                .last()
+                .map(|e| e.date.date_naive().succ_opt().unwrap_or(today))
+                .unwrap_or(today);
+
+            // stop short of the notices line.
+            let top = DAILY_FORECAST_TOP + (rows * DAILY_FORECAST_ROW_HEIGHT);
+            let mut viewport = self
+                .graphics
+                .viewport((0, top), (WIDTH, NOTICES_TOP.saturating_sub(top)));
+            if rows != 0 {
+                viewport.hline((10, 0), WIDTH - 20, Thickness::Medium, Color::Gray13);
+                viewport = viewport.shift_down(30);
+            }
+
+            self.agenda(
+                viewport.padded_left(40).padded_right(40),
+                &data.events,
+                from,
+                today,
+                state().display.agenda_events,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Takes over the header while a watch or warning is in effect,
+    /// keeping only the clock. The most severe alert gets the banner; any
+    /// others are counted.
+    fn alerts(&self, alerts: &[Alert], time: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        let Some(alert) = alerts.first() else {
+            return Ok(());
+        };
+
+        let banner = self.graphics.viewport((10, 14), (WIDTH - 20, 72));
+
+        let (fill, border) = match alert.severity {
+            Severity::Extreme | Severity::Severe => (Some(Color::Gray12), Thickness::Heavy),
+            Severity::Moderate => (Some(Color::Gray14), Thickness::Medium),
+            Severity::Minor | Severity::Unknown => (None, Thickness::Thin),
+        };
+        if let Some(fill) = fill {
+            banner.fill_rect((0, 0), (banner.width(), banner.height()), fill);
+        }
+        let border = border as usize;
+        banner.fill_rect((0, 0), (banner.width(), border), Color::Black);
+        banner.fill_rect((0, banner.height() - border), (banner.width(), border), Color::Black);
+        banner.fill_rect((0, 0), (border, banner.height()), Color::Black);
+        banner.fill_rect((banner.width() - border, 0), (border, banner.height()), Color::Black);
+        if alert.severity == Severity::Extreme {
+            banner.fill_rect((0, 0), (24, banner.height()), Color::Black);
+        }
+
+        let text_vp = banner.padded_left(40).padded_right(20).shift_down(16);
+
+        let event_rect = text_vp.text(
+            &alert.event,
+            36.0,
+            &typewriter_bold()?,
+            HorizontalAlign::Left,
+            VerticalAlign::Top,
+            Darkness::Dark,
+        );
+
+        let mut detail = alert
+            .expires
+            .map(|e| format!("until {}", human_time(e)))
+            .unwrap_or_default();
+        if alerts.len() > 1 {
+            detail = format!("{} (+{} more)", detail, alerts.len() - 1)
+                .trim()
+                .to_string();
+        }
+        text_vp
+            .shift_right(event_rect.max.x as usize - text_vp.x_offset + 20)
+            .shift_down(6)
+            .text(
+                &detail,
+                28.0,
+                &typewriter()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+
+        text_vp.text(
+            &human_time(time.into()),
+            36.0,
+            &typewriter()?,
+            HorizontalAlign::Right,
+            VerticalAlign::Top,
+            Darkness::Dark,
+        );
+
+        Ok(())
+    }
+
+    fn notices(&self, notices: &[String]) -> Result<(), anyhow::Error> {
+        if notices.is_empty() {
+            return Ok(());
+        }
+
+        self.graphics.viewport((30, NOTICES_TOP), (WIDTH - 60, 30)).text(
+            &notices.join(" • "),
+            24.0,
+            &typewriter_bold()?,
+            HorizontalAlign::Center,
+            VerticalAlign::Top,
+            Darkness::Dark,
+        );
+
+        Ok(())
+    }
+
+    fn countdown<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        countdown: &Countdown,
+    ) -> Result<(), anyhow::Error> {
+        viewport.text(
+            &countdown.label(),
+            28.0,
+            &typewriter()?,
+            HorizontalAlign::Center,
+            VerticalAlign::Top,
+            Darkness::Medium,
+        );
+        Ok(())
+    }
+
+    fn agenda<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        events: &[Event],
+        from: NaiveDate,
+        today: NaiveDate,
+        limit: usize,
+    ) -> Result<(), anyhow::Error> {
+        let limit = limit.min(viewport.height() / AGENDA_ROW_HEIGHT);
+
+        let mut row_vp = viewport;
+        for (i, event) in upcoming(events, from).into_iter().take(limit).enumerate() {
+            if i != 0 {
+                row_vp = row_vp.shift_down(AGENDA_ROW_HEIGHT);
+            }
+
+            row_vp.text(
+                &relative_date(event.date, today),
+                28.0,
+                &typewriter_bold()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+
+            row_vp.shift_right(230).text(
+                &event
+                    .time
+                    .map(human_naive_time)
+                    .unwrap_or("all day".to_string()),
+                24.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Light,
+            );
+
+            row_vp.shift_right(360).padded_right(260).text(
+                &event.summary,
+                28.0,
+                &typewriter()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+
+            if let Some(calendar) = &event.calendar {
+                row_vp.text(
+                    calendar,
+                    22.0,
+                    &typewriter()?,
+                    HorizontalAlign::Right,
+                    VerticalAlign::Top,
+                    Darkness::Light,
+                );
+            }
+        }
+
         Ok(())
     }
 
@@ -305,23 +891,28 @@ impl<'p, P: Paint> Display<'p, P> {
         &self,
         viewport: ViewPort<'_, WIDTH, HEIGHT>,
         forecast: &[HourlyForecast],
+        daily: &[DailyForecast],
     ) -> Result<(), anyhow::Error> {
-        for (i, f) in forecast.iter().enumerate() {
+        match state().display.hourly_style {
+            HourlyStyle::Columns => self.hourly_columns(viewport, forecast, daily),
+            HourlyStyle::Graph => self.hourly_graph(viewport, forecast),
+        }
+    }
+
+    fn hourly_columns<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        forecast: &[HourlyForecast],
+        daily: &[DailyForecast],
+    ) -> Result<(), anyhow::Error> {
+        // longer horizons skip hours to keep to the same columns.
+        let step = forecast.len().div_ceil(HOURLY_COLUMNS).max(1);
+
+        for (i, f) in forecast.iter().step_by(step).enumerate() {
             let hour_vp = viewport.viewport(((112 * i) + 12, 0), (110, 200));
             //hour_vp.outline(Color::Black);
-            let hour = if f.date_time.hour() >= 12 {
-                if f.date_time.hour() == 12 {
-                    "Noon".to_string()
-                } else {
-                    format!("{}p", f.date_time.hour() - 12)
-                }
-            } else if f.date_time.hour() == 0 {
-                "Midnight".to_string()
-            } else {
-                format!("{}a", f.date_time.hour())
-            };
             hour_vp.text(
-                &hour,
+                &hour_label(f.date_time),
                 24.0,
                 &typewriter_bold()?,
                 HorizontalAlign::Center,
@@ -331,17 +922,20 @@ impl<'p, P: Paint> Display<'p, P> {
 
             let hour_vp = hour_vp.shift_down(32);
 
-            if let Some(Ok(icon)) = weather_icon(f.weather_icon) {
-                hour_vp.bmp(
-                    &trim_bmp(&icon),
-                    HorizontalAlign::Center,
-                    VerticalAlign::Top,
-                );
-            }
+            let condition = if is_night(f.date_time, daily) {
+                f.condition.at_night()
+            } else {
+                f.condition
+            };
+            hour_vp.bmp(
+                &trim_bmp(&weather_icon(condition)?),
+                HorizontalAlign::Center,
+                VerticalAlign::Top,
+            );
 
             let hour_vp = hour_vp.shift_down(86);
             hour_vp.text(
-                &format!("{}°", f.temperature.value),
+                &format!("{}°", f.temperature),
                 30.0,
                 &sanserif_bold()?,
                 HorizontalAlign::Center,
@@ -364,78 +958,154 @@ impl<'p, P: Paint> Display<'p, P> {
         Ok(())
     }
 
+    /// Temperature as a line over precipitation probability bars, one slot per hour.
+    fn hourly_graph<const WIDTH: usize, const HEIGHT: usize>(
+        &self,
+        viewport: ViewPort<'_, WIDTH, HEIGHT>,
+        forecast: &[HourlyForecast],
+    ) -> Result<(), anyhow::Error> {
+        if forecast.len() < 2 {
+            return Ok(());
+        }
+
+        let graph_vp = viewport.viewport((12, 0), (viewport.width() - 24, 200));
+        let label_every = forecast.len().div_ceil(HOURLY_COLUMNS).max(1);
+
+        // room above the line for temperature labels, and below for hours.
+        let line_top = 34;
+        let baseline = 164;
+        let bar_max = 70;
+
+        let minimum = forecast.iter().map(|e| e.temperature).fold(f32::MAX, f32::min);
+        let maximum = forecast.iter().map(|e| e.temperature).fold(f32::MIN, f32::max);
+        let range = (maximum - minimum).max(1.0);
+        let y_for = |temperature: f32| {
+            baseline - 10 - ((temperature - minimum) / range * (baseline - 10 - line_top) as f32) as usize
+        };
+
+        let slot = graph_vp.width() / forecast.len();
+        let bar_width = slot.saturating_sub(4).max(1);
+
+        graph_vp.hline((0, baseline), graph_vp.width(), Thickness::Thin, Color::Gray12);
+
+        let mut points = vec![];
+        for (i, f) in forecast.iter().enumerate() {
+            let bar_height = f.precipitation_probability as usize * bar_max / 100;
+            graph_vp.fill_rect(
+                (i * slot + 2, baseline - bar_height),
+                (bar_width, bar_height),
+                Color::Gray11,
+            );
+
+            let x = i * slot + slot / 2;
+            let y = y_for(f.temperature);
+            points.push((x, y));
+
+            if i % label_every == 0 {
+                graph_vp.viewport((x.saturating_sub(55), y - 34), (110, 30)).text(
+                    &format!("{}°", f.temperature),
+                    24.0,
+                    &sanserif_bold()?,
+                    HorizontalAlign::Center,
+                    VerticalAlign::Top,
+                    Darkness::Dark,
+                );
+                graph_vp.viewport((x.saturating_sub(55), baseline + 6), (110, 30)).text(
+                    &hour_label(f.date_time),
+                    22.0,
+                    &typewriter_bold()?,
+                    HorizontalAlign::Center,
+                    VerticalAlign::Top,
+                    Darkness::Dark,
+                );
+            }
+        }
+
+        graph_vp.polyline(&points, Thickness::Medium, Color::Black);
+
+        Ok(())
+    }
+
     fn day_forecast<const WIDTH: usize, const HEIGHT: usize>(
         &self,
         viewport: ViewPort<'_, WIDTH, HEIGHT>,
         forecast: &DailyForecast,
         events: &[Event],
     ) -> Result<(), anyhow::Error> {
+        let details = state().display.daily_details;
         let viewport = viewport.shift_right(30);
 
-        let moonphase_vp = viewport.viewport((1230, 0), (200, 150));
+        if let Some(moon) = &forecast.moon {
+            let moonphase_vp = viewport.viewport((1230, 0), (200, 150));
 
-        if let Some(Ok(moonphase_icon)) = moonphase_icon(&forecast.moon.phase) {
             moonphase_vp.bmp(
-                &trim_bmp(&moonphase_icon),
+                &trim_bmp(&moonphase_icon(moon.phase)?),
                 HorizontalAlign::Left,
                 VerticalAlign::Top,
             );
-        }
 
-        let moonphase_vp = moonphase_vp.shift_down(120);
+            let moonphase_vp = moonphase_vp.shift_down(120);
 
-        let (rise_shift, set_shift) = match (forecast.moon.rise, forecast.moon.set) {
-            (Some(rise), Some(set)) => {
-                if rise < set {
-                    (0, 25)
-                } else {
-                    (25, 0)
+            let (rise_shift, set_shift) = match (moon.rise, moon.set) {
+                (Some(rise), Some(set)) => {
+                    if rise < set {
+                        (0, 25)
+                    } else {
+                        (25, 0)
+                    }
                 }
-            }
-            (Some(_rise), None) => (0, 25),
-            (None, Some(_set)) => (0, 25),
+                (Some(_rise), None) => (0, 25),
+                (None, Some(_set)) => (0, 25),
 
-            (None, None) => (0, 25),
-        };
+                (None, None) => (0, 25),
+            };
 
-        let rise_vp = moonphase_vp.shift_down(rise_shift);
+            let rise_vp = moonphase_vp.shift_down(rise_shift);
 
-        rise_vp.bmp(
-            &trim_bmp(&arrow_small_up()?),
-            HorizontalAlign::Left,
-            VerticalAlign::Top,
-        );
-        rise_vp.shift_right(35).text(
-            &forecast
-                .moon
-                .rise
-                .map(human_time)
-                .unwrap_or("--".to_string()),
-            24.0,
-            &sanserif()?,
-            HorizontalAlign::Left,
-            VerticalAlign::Top,
-            Darkness::Dark,
-        );
+            rise_vp.bmp(
+                &trim_bmp(&arrow_small_up()?),
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+            );
+            rise_vp.shift_right(35).text(
+                &moon
+                    .rise
+                    .map(human_time)
+                    .unwrap_or("--".to_string()),
+                24.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
 
-        let set_vp = moonphase_vp.shift_down(set_shift);
-        set_vp.bmp(
-            &trim_bmp(&arrow_small_down()?),
-            HorizontalAlign::Left,
-            VerticalAlign::Top,
-        );
-        set_vp.shift_right(35).text(
-            &forecast
-                .moon
-                .set
-                .map(human_time)
-                .unwrap_or("--".to_string()),
-            24.0,
-            &sanserif()?,
-            HorizontalAlign::Left,
-            VerticalAlign::Top,
-            Darkness::Dark,
-        );
+            let set_vp = moonphase_vp.shift_down(set_shift);
+            set_vp.bmp(
+                &trim_bmp(&arrow_small_down()?),
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+            );
+            set_vp.shift_right(35).text(
+                &moon
+                    .set
+                    .map(human_time)
+                    .unwrap_or("--".to_string()),
+                24.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+
+            moonphase_vp.shift_down(50).text(
+                &format!("{:.0}% lit", moon.illumination * 100.0),
+                22.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Light,
+            );
+        }
 
         let today_vp = viewport.shift_right(150);
         today_vp.text(
@@ -447,13 +1117,8 @@ impl<'p, P: Paint> Display<'p, P> {
             Darkness::Dark,
         );
 
-        let cleaned_phrase = forecast
-            .day
-            .long_phrase
-            .replace("; check AccuWeather frequently", "");
-
         today_vp.shift_down(114).padded_right(200).text(
-            &cleaned_phrase,
+            &forecast.day.phrase,
             30.0,
             &typewriter()?,
             HorizontalAlign::Left,
@@ -466,7 +1131,7 @@ impl<'p, P: Paint> Display<'p, P> {
         let temp_vp = stats_vp.viewport((0, 0), (140, 50));
 
         let min_rect = temp_vp.text(
-            &format!("{}°", forecast.temperature.minimum.value),
+            &format!("{}°", forecast.temperature.minimum),
             42.0,
             &typewriter_bold()?,
             HorizontalAlign::Right,
@@ -477,7 +1142,7 @@ impl<'p, P: Paint> Display<'p, P> {
         temp_vp
             .padded_right(min_rect.width() as usize + 10)
             .text(
-                &format!("{}°", forecast.temperature.maximum.value),
+                &format!("{}°", forecast.temperature.maximum),
                 42.0,
                 &typewriter_bold()?,
                 HorizontalAlign::Right,
@@ -497,12 +1162,11 @@ impl<'p, P: Paint> Display<'p, P> {
                 Darkness::Dark,
             );
 
-            let total_precip = forecast.day.snow.value
-                + forecast.night.snow.value
-                + forecast.day.rain.value
-                + forecast.night.rain.value
-                + forecast.day.ice.value
-                + forecast.night.ice.value;
+            let mut total_precip =
+                forecast.day.total_precipitation() + forecast.night.total_precipitation();
+            if details.contains(&DailyDetail::Snow) {
+                total_precip -= forecast.day.snow + forecast.night.snow;
+            }
 
             if total_precip > 0.0 {
                 rain_vp.shift_right(prob_rect.width() as usize + 10).text(
@@ -516,48 +1180,103 @@ impl<'p, P: Paint> Display<'p, P> {
             }
         }
 
-        let sun_vp = viewport.viewport((0, 0), (100, 150));
+        let mut detail_line = vec![];
+        for detail in &details {
+            match detail {
+                DailyDetail::Night => {
+                    let night = &forecast.night;
+                    if night.condition != Condition::Unknown {
+                        detail_line.push(format!(
+                            "night {} {}°",
+                            night.condition.label().to_lowercase(),
+                            forecast.temperature.minimum
+                        ));
+                    }
+                }
+                DailyDetail::Wind => {
+                    if let Some(wind) = &forecast.day.wind {
+                        let mut text =
+                            format!("wind {:.0} mph {}", wind.speed, wind.compass());
+                        if let Some(gust) = &forecast.day.wind_gust {
+                            if gust.speed > wind.speed {
+                                text = format!("{}, gusts {:.0}", text, gust.speed);
+                            }
+                        }
+                        detail_line.push(text);
+                    }
+                }
+                DailyDetail::Snow => {
+                    let snow = forecast.day.snow + forecast.night.snow;
+                    if snow > 0.0 {
+                        detail_line.push(format!("{:.1}\" snow", snow));
+                    }
+                }
+            }
+        }
 
-        if let Some(Ok(weather_icon)) = weather_icon(forecast.day.icon) {
-            //let icon_vp = viewport.viewport((500, 0), (200, 200));
-            sun_vp.bmp(
-                &trim_bmp(&weather_icon),
-                HorizontalAlign::Center,
+        if !detail_line.is_empty() {
+            today_vp.shift_down(62).padded_right(200).text(
+                &detail_line.join(" • "),
+                24.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
                 VerticalAlign::Top,
+                Darkness::Medium,
             );
         }
 
-        let sun_vp = sun_vp.shift_down(120);
+        let sun_vp = viewport.viewport((0, 0), (100, 150));
+
+        //let icon_vp = viewport.viewport((500, 0), (200, 200));
         sun_vp.bmp(
-            &trim_bmp(&arrow_small_up()?),
-            HorizontalAlign::Left,
+            &trim_bmp(&weather_icon(forecast.day.condition)?),
+            HorizontalAlign::Center,
             VerticalAlign::Top,
         );
 
-        sun_vp.shift_right(35).text(
-            &human_time(forecast.sun.rise),
-            24.0,
-            &sanserif()?,
-            HorizontalAlign::Left,
-            VerticalAlign::Top,
-            Darkness::Dark,
-        );
+        if let Some(sun) = &forecast.sun {
+            let sun_vp = sun_vp.shift_down(120);
+            sun_vp.bmp(
+                &trim_bmp(&arrow_small_up()?),
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+            );
 
-        let sun_vp = sun_vp.shift_down(25);
-        sun_vp.bmp(
-            &trim_bmp(&arrow_small_down()?),
-            HorizontalAlign::Left,
-            VerticalAlign::Top,
-        );
+            sun_vp.shift_right(35).text(
+                &human_time(sun.rise),
+                24.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
 
-        sun_vp.shift_right(35).text(
-            &human_time(forecast.sun.set),
-            24.0,
-            &sanserif()?,
-            HorizontalAlign::Left,
-            VerticalAlign::Top,
-            Darkness::Dark,
-        );
+            let sun_vp = sun_vp.shift_down(25);
+            sun_vp.bmp(
+                &trim_bmp(&arrow_small_down()?),
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+            );
+
+            sun_vp.shift_right(35).text(
+                &human_time(sun.set),
+                24.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Dark,
+            );
+
+            let day_length = sun.day_length();
+            sun_vp.shift_down(25).text(
+                &format!("{}h {}m", day_length.num_hours(), day_length.num_minutes() % 60),
+                22.0,
+                &sanserif()?,
+                HorizontalAlign::Left,
+                VerticalAlign::Top,
+                Darkness::Light,
+            );
+        }
 
         let todays_events: Vec<_> = events
             .iter()
@@ -679,9 +1398,21 @@ pub fn relative_day_name(date: DateTime<Local>) -> String {
     }
 }
 
+pub fn relative_date(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "Today".to_string(),
+        1 => "Tomorrow".to_string(),
+        days => format!("in {} days", days),
+    }
+}
+
 pub fn human_time(date_time: DateTime<Local>) -> String {
-    let mut hour = date_time.hour();
-    let minute = date_time.minute();
+    human_naive_time(date_time.time())
+}
+
+pub fn human_naive_time(time: NaiveTime) -> String {
+    let mut hour = time.hour();
+    let minute = time.minute();
 
     let ampm = if hour >= 12 {
         if hour > 12 {
@@ -698,69 +1429,135 @@ pub fn human_time(date_time: DateTime<Local>) -> String {
     format!("{}:{:0>2}{ampm}", hour, minute)
 }
 
+fn hour_label(time: DateTime<Local>) -> String {
+    if time.hour() >= 12 {
+        if time.hour() == 12 {
+            "Noon".to_string()
+        } else {
+            format!("{}p", time.hour() - 12)
+        }
+    } else if time.hour() == 0 {
+        "Midnight".to_string()
+    } else {
+        format!("{}a", time.hour())
+    }
+}
+
 pub fn c_to_f(c: f64) -> f64 {
     (c * 9.0 / 5.0) + 32.0
 }
 
-pub fn moonphase_icon(phase: &str) -> Option<Result<Image, anyhow::Error>> {
+pub fn moonphase_icon(phase: MoonPhase) -> Result<Image, anyhow::Error> {
     match phase {
-        "WaningGibbous" => Some(moon_waning_gibbous()),
-        "WaxingGibbous" => Some(moon_waxing_gibbous()),
-        "WaningCrescent" => Some(moon_waning_crescent()),
-        "WaxingCrescent" => Some(moon_waxing_crescent()),
-        "FirstQuarter" => Some(moon_first_quarter()),
-        "ThirdQuarter" => Some(moon_third_quarter()),
-        "Full" => Some(moon_full()),
-        "New" => Some(moon_new()),
-        "Last" => Some(moon_third_quarter()),
-        _ => None,
+        MoonPhase::New => moon_new(),
+        MoonPhase::WaxingCrescent => moon_waxing_crescent(),
+        MoonPhase::FirstQuarter => moon_first_quarter(),
+        MoonPhase::WaxingGibbous => moon_waxing_gibbous(),
+        MoonPhase::Full => moon_full(),
+        MoonPhase::WaningGibbous => moon_waning_gibbous(),
+        MoonPhase::ThirdQuarter => moon_third_quarter(),
+        MoonPhase::WaningCrescent => moon_waning_crescent(),
+    }
+}
+
+/// Plots readings across the viewport, scaled to their own range.
+fn sparkline<const WIDTH: usize, const HEIGHT: usize>(
+    viewport: ViewPort<'_, WIDTH, HEIGHT>,
+    measurements: &[Measurement],
+    color: Color,
+) {
+    if measurements.len() < 2 {
+        return;
+    }
+
+    let min = measurements.iter().map(|e| e.value).fold(f32::MAX, f32::min);
+    let max = measurements.iter().map(|e| e.value).fold(f32::MIN, f32::max);
+    let range = (max - min).max(1.0);
+
+    let step = (viewport.width() - 1) as f32 / (measurements.len() - 1) as f32;
+    let height = (viewport.height() - 1) as f32;
+
+    let points: Vec<_> = measurements
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            let x = (i as f32 * step) as usize;
+            let y = (height - ((e.value - min) / range) * height) as usize;
+            (x, y)
+        })
+        .collect();
+
+    for pair in points.windows(2) {
+        viewport.line(pair[0], pair[1], Thickness::Thin, color);
     }
 }
 
-pub fn weather_icon(icon: u8) -> Option<Result<Image, anyhow::Error>> {
-    match icon {
-        1 => Some(weather::sunny()),
-        2 => Some(weather::partly_cloudy()),
-        3 => Some(weather::partly_cloudy()),
-        4 => Some(weather::scattered_clouds()),
-        5 => Some(weather::sunny()),
-        6 => Some(weather::partly_cloudy()),
-        7 => Some(weather::clouds()),
-        8 => Some(weather::clouds()),
-        11 => Some(weather::fog()),
-        12 => Some(weather::heavy_rain()),
-        13 => Some(weather::partly_cloudy_rain()),
-        14 => Some(weather::partly_cloudy_rain()),
-        15 => Some(weather::thunderstorms()),
-        16 => Some(weather::partly_cloudy_thunderstorm()),
-        17 => Some(weather::partly_cloudy_thunderstorm()),
-        18 => Some(weather::heavy_rain()),
-        19 => Some(weather::flurries()),
-        20 => Some(weather::flurries()),
-        21 => Some(weather::flurries()),
-        22 => Some(weather::snow()),
-        23 => Some(weather::snow()),
-        24 => Some(weather::ice()),
-        25 => Some(weather::sleet()),
-        26 => Some(weather::sleet()),
-        29 => Some(weather::sleet()),
-        32 => Some(weather::windy()),
-        33 => Some(weather::sunny()),
-        34 => Some(weather::partly_cloudy()),
-        35 => Some(weather::partly_cloudy()),
-        36 => Some(weather::partly_cloudy()),
-        37 => Some(weather::partly_cloudy()),
-        38 => Some(weather::partly_cloudy()),
-        39 => Some(weather::partly_cloudy_rain()),
-        40 => Some(weather::partly_cloudy_rain()),
-        41 => Some(weather::partly_cloudy_thunderstorm()),
-        42 => Some(weather::partly_cloudy_thunderstorm()),
-        43 => Some(weather::flurries()),
-        44 => Some(weather::snow()),
-        _ => None,
+pub fn trend_icon(trend: &Trend) -> Result<Image, anyhow::Error> {
+    match trend {
+        Trend::Up => arrow_up(),
+        Trend::Down => arrow_down(),
+        Trend::Stable => arrow_level(),
     }
 }
 
+pub fn weather_icon(condition: Condition) -> Result<Image, anyhow::Error> {
+    match condition {
+        Condition::Clear => weather::sunny(),
+        Condition::ClearNight => moon_waxing_crescent(),
+        Condition::PartlyCloudy => weather::partly_cloudy(),
+        Condition::PartlyCloudyNight => night_icon(weather::clouds()?),
+        // the art AccuWeather's "mostly cloudy" has always had.
+        Condition::MostlyCloudy => weather::partly_cloudy(),
+        Condition::MostlyCloudyNight => night_icon(weather::clouds()?),
+        Condition::Cloudy => weather::clouds(),
+        Condition::Fog => weather::fog(),
+        Condition::Showers => weather::partly_cloudy_rain(),
+        Condition::ShowersNight => night_icon(weather::heavy_rain()?),
+        Condition::Rain => weather::heavy_rain(),
+        Condition::Thunderstorms => weather::thunderstorms(),
+        Condition::ScatteredThunderstorms => weather::partly_cloudy_thunderstorm(),
+        Condition::ScatteredThunderstormsNight => night_icon(weather::thunderstorms()?),
+        Condition::Flurries => weather::flurries(),
+        Condition::Snow => weather::snow(),
+        Condition::Ice => weather::ice(),
+        Condition::Sleet => weather::sleet(),
+        Condition::Windy => weather::windy(),
+        // there is no art for these; the nearest thing will do.
+        Condition::Hot => weather::sunny(),
+        Condition::Cold => weather::ice(),
+        Condition::Unknown => Ok(lighten_bmp(&weather::clouds()?, 0.4, false)),
+    }
+}
+
+/// A small moon tucked behind the top-left of the day art's clouds, in
+/// place of the sun.
+fn night_icon(cover: Image) -> Result<Image, anyhow::Error> {
+    let moon = scale_bmp(&moon_waxing_crescent()?, 0.4);
+    let cover = scale_bmp(&cover, 0.8);
+
+    let mut icon = Image::new(100, 100);
+    for x in 0..icon.get_width() {
+        for y in 0..icon.get_height() {
+            icon.set_pixel(x, y, bmp::consts::WHITE);
+        }
+    }
+
+    let icon = overlay_bmp(&icon, &moon, (0, 0));
+    Ok(overlay_bmp(&icon, &cover, (20, 20)))
+}
+
+/// Whether `time` is between dusk and dawn on its date, going by the daily
+/// forecast's civil twilight, or its sunset and sunrise when twilight is
+/// unknown.
+fn is_night(time: DateTime<Local>, daily: &[DailyForecast]) -> bool {
+    daily
+        .iter()
+        .find(|e| e.date.date_naive() == time.date_naive())
+        .and_then(|e| e.sun.as_ref())
+        .map(|sun| time < sun.dawn.unwrap_or(sun.rise) || time >= sun.dusk.unwrap_or(sun.set))
+        .unwrap_or(false)
+}
+
 fn wind_direction_icon(wind_angle: i16) -> Result<Image, anyhow::Error> {
     let wind = wind::wind()?;
 
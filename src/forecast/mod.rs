@@ -0,0 +1,271 @@
+use crate::accuweather::AccuWeatherClient;
+use crate::astronomy::{self, MoonPhase};
+use crate::nws::NwsClient;
+use crate::openmeteo::OpenMeteoClient;
+use crate::state::{state, ForecastProvider};
+use chrono::{DateTime, Duration, Local};
+
+/// What the sky is doing, independent of which provider said so. Each
+/// provider maps its own codes onto these, and the display picks icons
+/// from them. Conditions where the sun would show have a night variant.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Condition {
+    Clear,
+    ClearNight,
+    PartlyCloudy,
+    PartlyCloudyNight,
+    MostlyCloudy,
+    MostlyCloudyNight,
+    Cloudy,
+    Fog,
+    Showers,
+    ShowersNight,
+    Rain,
+    Thunderstorms,
+    ScatteredThunderstorms,
+    ScatteredThunderstormsNight,
+    Flurries,
+    Snow,
+    Ice,
+    Sleet,
+    Windy,
+    Hot,
+    Cold,
+    /// A code the provider's mapping doesn't know.
+    #[default]
+    Unknown,
+}
+
+impl Condition {
+    /// The night variant, for conditions that have one.
+    pub fn at_night(self) -> Self {
+        match self {
+            Condition::Clear => Condition::ClearNight,
+            Condition::PartlyCloudy => Condition::PartlyCloudyNight,
+            Condition::MostlyCloudy => Condition::MostlyCloudyNight,
+            Condition::Showers => Condition::ShowersNight,
+            Condition::ScatteredThunderstorms => Condition::ScatteredThunderstormsNight,
+            other => other,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Condition::Clear | Condition::ClearNight => "Clear",
+            Condition::PartlyCloudy | Condition::PartlyCloudyNight => "Partly cloudy",
+            Condition::MostlyCloudy | Condition::MostlyCloudyNight => "Mostly cloudy",
+            Condition::Cloudy => "Cloudy",
+            Condition::Fog => "Fog",
+            Condition::Showers | Condition::ShowersNight => "Showers",
+            Condition::Rain => "Rain",
+            Condition::Thunderstorms => "Thunderstorms",
+            Condition::ScatteredThunderstorms | Condition::ScatteredThunderstormsNight => {
+                "Scattered thunderstorms"
+            }
+            Condition::Flurries => "Flurries",
+            Condition::Snow => "Snow",
+            Condition::Ice => "Freezing rain",
+            Condition::Sleet => "Sleet",
+            Condition::Windy => "Windy",
+            Condition::Hot => "Hot",
+            Condition::Cold => "Cold",
+            Condition::Unknown => "",
+        }
+    }
+}
+
+/// One day of forecast. Temperatures are °F, precipitation inches and wind
+/// mph, whatever the provider reports natively.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyForecast {
+    pub date: DateTime<Local>,
+    /// Not every provider forecasts the sun and moon.
+    pub sun: Option<Sun>,
+    pub moon: Option<Moon>,
+    pub temperature: Temperature,
+    pub day: Details,
+    pub night: Details,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sun {
+    pub rise: DateTime<Local>,
+    pub set: DateTime<Local>,
+    /// Civil twilight, when it's light enough outside without the sun.
+    pub dawn: Option<DateTime<Local>>,
+    pub dusk: Option<DateTime<Local>>,
+}
+
+impl Sun {
+    pub fn day_length(&self) -> Duration {
+        self.set - self.rise
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Moon {
+    pub rise: Option<DateTime<Local>>,
+    pub set: Option<DateTime<Local>>,
+    pub phase: MoonPhase,
+    /// Fraction of the disc that is lit, 0 to 1.
+    pub illumination: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Temperature {
+    pub minimum: f32,
+    pub maximum: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Details {
+    pub condition: Condition,
+    pub phrase: String,
+    pub precipitation_probability: u8,
+    pub rain: f32,
+    pub snow: f32,
+    pub ice: f32,
+    pub wind: Option<Wind>,
+    pub wind_gust: Option<Wind>,
+}
+
+impl Details {
+    pub fn total_precipitation(&self) -> f32 {
+        self.rain + self.snow + self.ice
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wind {
+    pub speed: f32,
+    pub degrees: u16,
+}
+
+/// The 16 compass points, clockwise from north.
+const COMPASS_POINTS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+
+impl Wind {
+    /// The 16-point compass direction the wind is coming from.
+    pub fn compass(&self) -> &'static str {
+        COMPASS_POINTS[((self.degrees as f32 / 22.5).round() as usize) % 16]
+    }
+
+    /// Degrees for a compass point such as `NNW`.
+    pub fn from_compass(direction: &str) -> Option<u16> {
+        COMPASS_POINTS
+            .iter()
+            .position(|e| *e == direction)
+            .map(|i| (i as f32 * 22.5).round() as u16)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourlyForecast {
+    pub date_time: DateTime<Local>,
+    pub temperature: f32,
+    pub precipitation_probability: u8,
+    pub condition: Condition,
+    pub phrase: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Unknown,
+    Minor,
+    Moderate,
+    Severe,
+    Extreme,
+}
+
+/// A watch, warning or advisory in effect for the location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub event: String,
+    pub headline: Option<String>,
+    pub severity: Severity,
+    pub onset: Option<DateTime<Local>>,
+    pub expires: Option<DateTime<Local>>,
+}
+
+/// The forecast provider picked by `[forecast] provider`.
+pub enum ForecastClient {
+    AccuWeather(AccuWeatherClient),
+    OpenMeteo(OpenMeteoClient),
+    Nws(NwsClient),
+}
+
+impl ForecastClient {
+    pub fn new() -> Self {
+        match state().forecast.provider {
+            ForecastProvider::AccuWeather => ForecastClient::AccuWeather(AccuWeatherClient::new()),
+            ForecastProvider::OpenMeteo => ForecastClient::OpenMeteo(OpenMeteoClient::new()),
+            ForecastProvider::Nws => ForecastClient::Nws(NwsClient::new()),
+        }
+    }
+
+    /// AccuWeather's adapts to its quota; the others are free to call.
+    pub fn cadence(&self) -> Duration {
+        match self {
+            ForecastClient::AccuWeather(client) => client.cadence(),
+            _ => Duration::minutes(30),
+        }
+    }
+
+    pub async fn daily_forecast(&self) -> Result<Vec<DailyForecast>, anyhow::Error> {
+        let forecast = match self {
+            ForecastClient::AccuWeather(client) => client.daily_forecast().await?,
+            ForecastClient::OpenMeteo(client) => client.daily_forecast().await?,
+            ForecastClient::Nws(client) => client.daily_forecast().await?,
+        };
+
+        Ok(forecast.into_iter().map(with_astronomy).collect())
+    }
+
+    pub async fn hourly_forecasts(&self) -> Result<Vec<HourlyForecast>, anyhow::Error> {
+        match self {
+            ForecastClient::AccuWeather(client) => client.hourly_forecasts().await,
+            ForecastClient::OpenMeteo(client) => client.hourly_forecasts().await,
+            ForecastClient::Nws(client) => client.hourly_forecasts().await,
+        }
+    }
+}
+
+/// Fills in whatever the provider left out of the sun and moon from our
+/// own calculations. Providers' rise and set times are kept when given.
+fn with_astronomy(mut forecast: DailyForecast) -> DailyForecast {
+    let location = state().location;
+    let date = forecast.date.date_naive();
+
+    let sun = astronomy::sun(date, location.lat, location.lon);
+    match &mut forecast.sun {
+        Some(provided) => {
+            provided.dawn = provided.dawn.or(sun.dawn);
+            provided.dusk = provided.dusk.or(sun.dusk);
+        }
+        None => {
+            if let (Some(rise), Some(set)) = (sun.rise, sun.set) {
+                forecast.sun = Some(Sun {
+                    rise,
+                    set,
+                    dawn: sun.dawn,
+                    dusk: sun.dusk,
+                });
+            }
+        }
+    }
+
+    if forecast.moon.is_none() {
+        let moon = astronomy::moon(date, location.lat, location.lon);
+        forecast.moon = Some(Moon {
+            rise: moon.rise,
+            set: moon.set,
+            phase: moon.phase,
+            illumination: moon.illumination,
+        });
+    }
+
+    forecast
+}
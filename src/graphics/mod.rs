@@ -319,6 +319,62 @@ impl<'g, const WIDTH: usize, const HEIGHT: usize> ViewPort<'g, WIDTH, HEIGHT> {
         }
     }
 
+    /// A solid rectangle with its top-left corner at `(x, y)`.
+    pub fn fill_rect(&self, (x, y): (usize, usize), (width, height): (usize, usize), color: Color) {
+        for row in 0..height {
+            for col in 0..width {
+                self.set((x + col, y + row), color);
+            }
+        }
+    }
+
+    /// A straight line between two points, drawn `thickness` pixels wide.
+    pub fn line(
+        &self,
+        (x0, y0): (usize, usize),
+        (x1, y1): (usize, usize),
+        thickness: Thickness,
+        color: Color,
+    ) {
+        let (x0, y0, x1, y1) = (x0 as i64, y0 as i64, x1 as i64, y1 as i64);
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+        loop {
+            for offset in 0..(thickness as i64) {
+                // widen across the line's minor axis.
+                let (px, py) = if dx > -dy { (x, y + offset) } else { (x + offset, y) };
+                if px >= 0 && py >= 0 {
+                    self.set((px as usize, py as usize), color);
+                }
+            }
+
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Connected line segments through `points`, in order.
+    pub fn polyline(&self, points: &[(usize, usize)], thickness: Thickness, color: Color) {
+        for segment in points.windows(2) {
+            self.line(segment[0], segment[1], thickness, color);
+        }
+    }
+
     pub fn bmp(&self, image: &Image, h_align: HorizontalAlign, v_align: VerticalAlign) {
         let x_offset = match h_align {
             HorizontalAlign::Left => self.x_offset,
@@ -635,6 +691,26 @@ pub fn trim_bmp(image: &Image) -> Image {
     trimmed
 }
 
+/// Draws the non-white pixels of `overlay` over a copy of `base`.
+pub fn overlay_bmp(base: &Image, overlay: &Image, (x_offset, y_offset): (u32, u32)) -> Image {
+    let mut combined = base.clone();
+
+    for x in 0..overlay.get_width() {
+        for y in 0..overlay.get_height() {
+            let (x_to, y_to) = (x + x_offset, y + y_offset);
+            if x_to >= combined.get_width() || y_to >= combined.get_height() {
+                continue;
+            }
+            let pixel = overlay.get_pixel(x, y);
+            if !matches!(Color::from(pixel), Color::White) {
+                combined.set_pixel(x_to, y_to, pixel);
+            }
+        }
+    }
+
+    combined
+}
+
 pub fn scale_bmp(image: &Image, scale: f32) -> Image {
     let mut scaled = Image::new((image.get_width() as f32 * scale) as u32, (image.get_height() as f32 * scale) as u32);
 
@@ -3,18 +3,25 @@ use crate::paint::{NoOpPaint, Paint};
 use clap::Parser;
 
 mod accuweather;
+mod aqi;
 pub mod art;
+mod astronomy;
 mod calendar;
 mod cli;
+mod countdown;
 mod data;
 mod display;
 mod font;
+mod forecast;
 mod graphics;
 mod netatmo;
+mod nws;
+mod openmeteo;
 mod paint;
 mod purple;
 mod state;
 mod birdnet;
+mod birthdays;
 
 //pub const LAT: &str ="36.949817";
 //pub const LON: &str = "-81.077840";
@@ -40,9 +47,18 @@ async fn main() -> Result<(), anyhow::Error> {
         Command::Screen(inner) => {
             inner.run(&mut paint).await?;
         }
+        Command::Agenda(inner) => {
+            inner.run(&mut paint).await?;
+        }
+        Command::Air(inner) => {
+            inner.run(&mut paint).await?;
+        }
         Command::Loop(inner) => {
             inner.run(&mut paint).await?;
         }
+        Command::Netatmo(inner) => {
+            inner.run().await?;
+        }
         Command::Test(inner) => {
             inner.run(&mut paint).await?;
         }
@@ -1,9 +1,15 @@
 #![allow(unused)]
 
 use crate::netatmo::station_data::Envelope;
-use crate::state::{state, update_state};
+use crate::state::{reload_state, state, update_state, NetatmoModules};
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
 use serde::Deserialize;
+use reqwest::{StatusCode, Url};
 use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use crate::accuweather::daily_forecast::Snow;
 
 mod station_data;
@@ -12,109 +18,457 @@ const AUTH_URL: &str = "https://api.netatmo.com/oauth2/authorize";
 const TOKEN_URL: &str = "https://api.netatmo.com/oauth2/token";
 
 const GET_STATIONS_DATA: &str = "https://api.netatmo.com/api/getstationsdata";
+const GET_MEASURE: &str = "https://api.netatmo.com/api/getmeasure";
+
+const HISTORY_HOURS: i64 = 24;
 
 #[derive(Deserialize, Debug)]
 pub struct RefreshedToken {
     access_token: String,
     refresh_token: String,
+    expires_in: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenError {
+    error: String,
+}
+
+#[derive(Debug)]
+pub enum NetatmoError {
+    /// The refresh token was revoked or has expired; a human needs to log in again.
+    ReauthorizationRequired,
+}
+
+impl Display for NetatmoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetatmoError::ReauthorizationRequired => {
+                write!(f, "Netatmo needs re-authorization: run `lattitude netatmo login`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetatmoError {}
+
+const SCOPE: &str = "read_station";
+
+/// The page a human visits to grant access; Netatmo redirects back to
+/// `redirect_uri` with `code` and `state` query parameters.
+pub fn authorize_url(redirect_uri: &str, csrf_state: &str) -> Result<Url, anyhow::Error> {
+    let netatmo = state().netatmo;
+
+    Ok(Url::parse_with_params(
+        AUTH_URL,
+        &[
+            ("client_id", netatmo.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("scope", SCOPE),
+            ("state", csrf_state),
+        ],
+    )?)
 }
 
-pub async fn get_client() -> Result<NetatmoClient, anyhow::Error> {
-    let state = state().netatmo;
+/// Trades an authorization code for tokens and saves the refresh token.
+pub async fn exchange_code(code: &str, redirect_uri: &str) -> Result<(), anyhow::Error> {
+    let netatmo = state().netatmo;
 
-    let client = reqwest::Client::new();
-    let result = client
+    let result = reqwest::Client::new()
         .post(TOKEN_URL)
         .form(&[
-            ("grant_type", "refresh_token"),
-            ("refresh_token", &state.refresh_token),
-            ("client_id", &state.client_id),
-            ("client_secret", &state.client_secret),
+            ("grant_type", "authorization_code"),
+            ("client_id", &netatmo.client_id),
+            ("client_secret", &netatmo.client_secret),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("scope", SCOPE),
         ])
         .send()
         .await?;
 
-    let refreshed: RefreshedToken = result.json().await?;
+    if !result.status().is_success() {
+        let status = result.status();
+        let error: Option<TokenError> = result.json().await.ok();
+        return Err(anyhow!(
+            "netatmo authorization failed: {}",
+            error.map(|e| e.error).unwrap_or(status.to_string())
+        ));
+    }
+
+    let tokens: RefreshedToken = result.json().await?;
+
+    update_state(|update| update.netatmo.refresh_token = tokens.refresh_token)
+}
+
+/// Refresh this long before the access token actually expires.
+const EXPIRY_MARGIN_MINUTES: i64 = 5;
+
+#[derive(Debug, Clone)]
+struct AccessToken {
+    access_token: String,
+    expires_at: DateTime<Utc>,
+}
 
-    if state.refresh_token != refreshed.refresh_token {
-        update_state(|update| update.netatmo.refresh_token = refreshed.refresh_token);
+/// Holds the current access token and only performs a refresh-token grant
+/// once it is about to expire.
+pub struct TokenManager {
+    token: RefCell<Option<AccessToken>>,
+    /// The refresh token that was rejected, so it isn't retried every fetch.
+    rejected: RefCell<Option<String>>,
+}
+
+impl TokenManager {
+    pub fn new() -> Self {
+        Self {
+            token: RefCell::new(None),
+            rejected: RefCell::new(None),
+        }
     }
 
-    Ok(NetatmoClient {
-        access_token: refreshed.access_token,
-    })
+    /// Compares against the config on disk, since a login from another
+    /// process replaces the refresh token there.
+    pub fn reauthorization_required(&self) -> bool {
+        let Some(rejected) = self.rejected.borrow().clone() else {
+            return false;
+        };
+
+        let current = match reload_state() {
+            Ok(state) => state.netatmo.refresh_token,
+            Err(err) => {
+                println!("unable to reload config: {}", err);
+                state().netatmo.refresh_token
+            }
+        };
+
+        rejected == current
+    }
+
+    /// Forget the access token, e.g. after the API turned it down.
+    pub fn invalidate(&self) {
+        self.token.borrow_mut().take();
+    }
+
+    /// Whether `err` was the API turning down the access token itself, as
+    /// opposed to the network or the service failing.
+    pub fn token_rejected(err: &anyhow::Error) -> bool {
+        matches!(
+            err.downcast_ref::<reqwest::Error>().and_then(|e| e.status()),
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+        )
+    }
+
+    pub async fn client(&self) -> Result<NetatmoClient, anyhow::Error> {
+        let cached = self.token.borrow().clone();
+        let token = match cached {
+            Some(token) if token.expires_at > Utc::now() => token,
+            _ => self.refresh().await?,
+        };
+
+        Ok(NetatmoClient {
+            access_token: token.access_token,
+        })
+    }
+
+    async fn refresh(&self) -> Result<AccessToken, anyhow::Error> {
+        if self.reauthorization_required() {
+            return Err(NetatmoError::ReauthorizationRequired.into());
+        }
+
+        let state = reload_state()?.netatmo;
+
+        let client = reqwest::Client::new();
+        let result = client
+            .post(TOKEN_URL)
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &state.refresh_token),
+                ("client_id", &state.client_id),
+                ("client_secret", &state.client_secret),
+            ])
+            .send()
+            .await?;
+
+        if !result.status().is_success() {
+            let status = result.status();
+            let error: Option<TokenError> = result.json().await.ok();
+            return match error {
+                Some(error) if error.error == "invalid_grant" => {
+                    self.rejected.borrow_mut().replace(state.refresh_token);
+                    Err(NetatmoError::ReauthorizationRequired.into())
+                }
+                Some(error) => Err(anyhow!("netatmo token refresh failed: {}", error.error)),
+                None => Err(anyhow!("netatmo token refresh failed: {}", status)),
+            };
+        }
+
+        let refreshed: RefreshedToken = result.json().await?;
+
+        if state.refresh_token != refreshed.refresh_token {
+            let refresh_token = refreshed.refresh_token.clone();
+            if let Err(err) = update_state(|update| update.netatmo.refresh_token = refresh_token) {
+                println!("unable to save netatmo refresh token: {}", err);
+            }
+        }
+
+        let token = AccessToken {
+            access_token: refreshed.access_token,
+            expires_at: Utc::now() + Duration::seconds(refreshed.expires_in)
+                - Duration::minutes(EXPIRY_MARGIN_MINUTES),
+        };
+
+        self.token.borrow_mut().replace(token.clone());
+
+        Ok(token)
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct NetatmoData {
-    pub inside: Vec<WeatherData>,
-    pub outside: Vec<WeatherData>,
+    pub modules: Vec<Module>,
+    pub selection: NetatmoModules,
+    pub history: Option<History>,
+}
+
+/// A station base or one of its modules, along with its latest readings.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub id: String,
+    pub name: String,
+    pub station_id: String,
+    pub station_name: String,
+    pub ty: ModuleType,
+    pub status: ModuleStatus,
+    pub data: Vec<WeatherData>,
+}
+
+impl Module {
+    /// Reads each data type from the dashboard, skipping any that fail to
+    /// parse. Unreachable modules report no dashboard at all.
+    fn read(&mut self, data_types: &[String], dashboard_data: Option<&Value>) {
+        let Some(dashboard_data) = dashboard_data else {
+            return;
+        };
+
+        for data_type in data_types {
+            match convert(data_type, dashboard_data) {
+                Ok(Some(result)) => self.data.push(result),
+                Ok(None) => {}
+                Err(err) => println!("netatmo {} {}: {}", self.name, data_type, err),
+            }
+        }
+    }
+
+    fn matches(&self, selector: &str) -> bool {
+        self.id == selector
+            || self.name == selector
+            || format!("{}/{}", self.station_name, self.name) == selector
+    }
+}
+
+/// Battery below this percentage is reported as low.
+const LOW_BATTERY_PERCENT: u8 = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleStatus {
+    pub reachable: bool,
+    pub battery_percent: Option<u8>,
+    /// `rf_status` for modules, `wifi_status` for the base; higher is weaker.
+    pub signal: Option<u16>,
+}
+
+impl ModuleStatus {
+    pub fn low_battery(&self) -> bool {
+        self.battery_percent
+            .map(|e| e < LOW_BATTERY_PERCENT)
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModuleType {
+    /// `NAMain`, the indoor base station.
+    Main,
+    /// `NAModule1`
+    Outdoor,
+    /// `NAModule2`
+    Wind,
+    /// `NAModule3`
+    Rain,
+    /// `NAModule4`, an additional indoor module.
+    Indoor,
+    Other(String),
+}
+
+impl From<&str> for ModuleType {
+    fn from(value: &str) -> Self {
+        match value {
+            "NAMain" => ModuleType::Main,
+            "NAModule1" => ModuleType::Outdoor,
+            "NAModule2" => ModuleType::Wind,
+            "NAModule3" => ModuleType::Rain,
+            "NAModule4" => ModuleType::Indoor,
+            other => ModuleType::Other(other.to_string()),
+        }
+    }
+}
+
+/// Which module feeds a widget.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Role {
+    Outdoor,
+    Indoor,
+    Wind,
+    Rain,
+    Pressure,
+}
+
+impl Role {
+    fn selector(self, selection: &NetatmoModules) -> Option<&String> {
+        match self {
+            Role::Outdoor => selection.outdoor.as_ref(),
+            Role::Indoor => selection.indoor.as_ref(),
+            Role::Wind => selection.wind.as_ref(),
+            Role::Rain => selection.rain.as_ref(),
+            Role::Pressure => selection.pressure.as_ref(),
+        }
+    }
+
+    fn default_type(self) -> ModuleType {
+        match self {
+            Role::Outdoor => ModuleType::Outdoor,
+            Role::Indoor => ModuleType::Main,
+            Role::Wind => ModuleType::Wind,
+            Role::Rain => ModuleType::Rain,
+            Role::Pressure => ModuleType::Main,
+        }
+    }
 }
 
 impl NetatmoData {
-    pub fn outside_temp(&self) -> Option<Temperature> {
-        self.outside
+    /// The configured module for a role, otherwise the first module of
+    /// the usual type for it.
+    pub fn module(&self, role: Role) -> Option<&Module> {
+        if let Some(selector) = role.selector(&self.selection) {
+            self.modules.iter().find(|e| e.matches(selector))
+        } else {
+            let ty = role.default_type();
+            self.modules.iter().find(|e| e.ty == ty)
+        }
+    }
+
+    /// Offline and low-battery warnings, one per affected module.
+    pub fn module_alerts(&self) -> Vec<String> {
+        self.modules
             .iter()
-            .find_map(|e| {
-                if let WeatherData::Temperature(temp) = e {
-                    Some(temp)
+            .filter_map(|module| {
+                if !module.status.reachable {
+                    Some(format!("{} offline", module.name))
+                } else if module.status.low_battery() {
+                    Some(format!(
+                        "{} low battery ({}%)",
+                        module.name,
+                        module.status.battery_percent.unwrap_or_default()
+                    ))
                 } else {
                     None
                 }
             })
-            .cloned()
+            .collect()
+    }
+
+    fn find<T: Clone>(&self, role: Role, f: impl Fn(&WeatherData) -> Option<&T>) -> Option<T> {
+        self.module(role)?.data.iter().find_map(f).cloned()
+    }
+
+    pub fn outside_temp(&self) -> Option<Temperature> {
+        self.find(Role::Outdoor, |e| {
+            if let WeatherData::Temperature(temp) = e {
+                Some(temp)
+            } else {
+                None
+            }
+        })
     }
 
     pub fn wind(&self) -> Option<Wind> {
-        self.outside
-            .iter()
-            .find_map(|e| {
-                if let WeatherData::Wind(wind) = e {
-                    Some(wind)
-                } else {
-                    None
-                }
-            })
-            .cloned()
+        self.find(Role::Wind, |e| {
+            if let WeatherData::Wind(wind) = e {
+                Some(wind)
+            } else {
+                None
+            }
+        })
     }
 
     pub fn rain(&self) -> Option<Rain> {
-        self.outside
-            .iter()
-            .find_map(|e| {
-                if let WeatherData::Rain(rain) = e {
-                    Some(rain)
-                } else {
-                    None
-                }
-            })
-            .cloned()
+        self.find(Role::Rain, |e| {
+            if let WeatherData::Rain(rain) = e {
+                Some(rain)
+            } else {
+                None
+            }
+        })
     }
 
     pub fn humidity(&self) -> Option<Humidity> {
-        self.outside
-            .iter()
-            .find_map(|e| {
-                if let WeatherData::Humidity(humidity) = e {
-                    Some(humidity)
-                } else {
-                    None
-                }
-            })
-            .cloned()
+        self.find(Role::Outdoor, |e| {
+            if let WeatherData::Humidity(humidity) = e {
+                Some(humidity)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn inside_temp(&self) -> Option<Temperature> {
+        self.find(Role::Indoor, |e| {
+            if let WeatherData::Temperature(temp) = e {
+                Some(temp)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn inside_humidity(&self) -> Option<Humidity> {
+        self.find(Role::Indoor, |e| {
+            if let WeatherData::Humidity(humidity) = e {
+                Some(humidity)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn co2(&self) -> Option<Co2> {
+        self.find(Role::Indoor, |e| {
+            if let WeatherData::Co2(co2) = e {
+                Some(co2)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn noise(&self) -> Option<Noise> {
+        self.find(Role::Indoor, |e| {
+            if let WeatherData::Noise(noise) = e {
+                Some(noise)
+            } else {
+                None
+            }
+        })
     }
 
     pub fn pressure(&self) -> Option<Pressure> {
-        self.outside
-            .iter()
-            .find_map(|e| {
-                if let WeatherData::Pressure(pressure) = e {
-                    Some(pressure)
-                } else {
-                    None
-                }
-            })
-            .cloned()
+        self.find(Role::Pressure, |e| {
+            if let WeatherData::Pressure(pressure) = e {
+                Some(pressure)
+            } else {
+                None
+            }
+        })
     }
 }
 
@@ -129,73 +483,177 @@ impl NetatmoClient {
             .post(GET_STATIONS_DATA)
             .header("Authorization", format!("Bearer {}", self.access_token))
             .send()
-            .await?;
+            .await?
+            .error_for_status()?;
 
         let data: Envelope = response.json().await?;
 
-        let mut netatmo_data = NetatmoData::default();
+        let mut netatmo_data = NetatmoData {
+            modules: vec![],
+            selection: state().netatmo.modules,
+            history: None,
+        };
 
         for device in data.body.devices {
-            for data_type in &device.data_type {
-                let dashboard_data = &device.dashboard_data;
-                if let Some(result) = convert(data_type, dashboard_data) {
-                    netatmo_data.inside.push(result);
-                }
-            }
+            let mut base = Module {
+                id: device._id.clone(),
+                name: device.module_name.clone().unwrap_or(device.station_name.clone()),
+                station_id: device._id.clone(),
+                station_name: device.station_name.clone(),
+                ty: device.ty.as_str().into(),
+                status: ModuleStatus {
+                    reachable: device.reachable.unwrap_or(true),
+                    battery_percent: None,
+                    signal: device.wifi_status,
+                },
+                data: vec![],
+            };
+            base.read(&device.data_type, device.dashboard_data.as_ref());
+            netatmo_data.modules.push(base);
+
             for module in device.modules {
-                if let Some(Value::Array(data_types)) = module.get("data_type") {
-                    for data_type in data_types {
-                        if data_type.is_string() {
-                            let data_type = data_type.as_str().unwrap();
-                            if let Some(dashboard_data) = module.get("dashboard_data") {
-                                if let Some(result) = convert(data_type, dashboard_data) {
-                                    netatmo_data.outside.push(result);
-                                }
-                            }
-                        }
-                    }
-                }
+                let text = |key| {
+                    module
+                        .get(key)
+                        .and_then(|e| e.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let number = |key| module.get(key).and_then(|e| e.as_u64());
+                let mut entry = Module {
+                    id: text("_id"),
+                    name: text("module_name"),
+                    station_id: device._id.clone(),
+                    station_name: device.station_name.clone(),
+                    ty: text("type").as_str().into(),
+                    status: ModuleStatus {
+                        reachable: module
+                            .get("reachable")
+                            .and_then(|e| e.as_bool())
+                            .unwrap_or(true),
+                        battery_percent: number("battery_percent").map(|e| e as u8),
+                        signal: number("rf_status").map(|e| e as u16),
+                    },
+                    data: vec![],
+                };
+                let data_types: Vec<String> = match module.get("data_type") {
+                    Some(Value::Array(data_types)) => data_types
+                        .iter()
+                        .filter_map(|e| e.as_str())
+                        .map(|e| e.to_string())
+                        .collect(),
+                    _ => vec![],
+                };
+                entry.read(&data_types, module.get("dashboard_data"));
+                netatmo_data.modules.push(entry);
             }
         }
 
         Ok(netatmo_data)
     }
-}
 
-fn convert(data_type: &str, dashboard_data: &Value) -> Option<WeatherData> {
-    match data_type {
-        "Wind" => {
-            let wind: Wind = dashboard_data.into();
-            Some(WeatherData::Wind(wind))
-        }
-        "Temperature" => {
-            let temperature: Temperature = dashboard_data.into();
-            Some(WeatherData::Temperature(temperature))
-        }
-        "Humidity" => {
-            let humidity: Humidity = dashboard_data.into();
-            Some(WeatherData::Humidity(humidity))
-        }
-        "Rain" => {
-            let rain: Rain = dashboard_data.into();
-            Some(WeatherData::Rain(rain))
-        }
-        "CO2" => {
-            let co2: Co2 = dashboard_data.into();
-            Some(WeatherData::Co2(co2))
+    /// Outdoor temperature and humidity plus station pressure for the last
+    /// day, from whichever modules feed those widgets.
+    pub async fn get_history(&self, data: &NetatmoData) -> Result<History, anyhow::Error> {
+        let mut history = History::default();
+        let since = Utc::now() - Duration::hours(HISTORY_HOURS);
+
+        // each series stands alone, so one failing doesn't lose the others.
+        if let Some(outdoor) = data.module(Role::Outdoor) {
+            match self
+                .get_measure(outdoor, &["temperature", "humidity"], since)
+                .await
+            {
+                Ok(measures) => {
+                    history.temperature = column(&measures, 0);
+                    history.humidity = column(&measures, 1);
+                }
+                Err(err) => println!("netatmo outdoor history: {}", err),
+            }
         }
-        "Noise" => {
-            let noise: Noise = dashboard_data.into();
-            Some(WeatherData::Noise(noise))
+
+        if let Some(station) = data.module(Role::Pressure) {
+            match self.get_measure(station, &["pressure"], since).await {
+                Ok(measures) => history.pressure = column(&measures, 0),
+                Err(err) => println!("netatmo pressure history: {}", err),
+            }
         }
-        "Pressure" => {
-            let pressure: Pressure = dashboard_data.into();
-            Some(WeatherData::Pressure(pressure))
+
+        Ok(history)
+    }
+
+    async fn get_measure(
+        &self,
+        module: &Module,
+        types: &[&str],
+        since: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, Vec<Option<f32>>)>, anyhow::Error> {
+        let mut query = vec![
+            ("device_id", module.station_id.clone()),
+            ("scale", "30min".to_string()),
+            ("type", types.join(",")),
+            ("date_begin", since.timestamp().to_string()),
+            ("optimize", "false".to_string()),
+            ("real_time", "true".to_string()),
+        ];
+        if module.id != module.station_id {
+            query.push(("module_id", module.id.clone()));
         }
-        _ => None,
+
+        let measure: MeasureEnvelope = reqwest::Client::new()
+            .get(GET_MEASURE)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let mut measures: Vec<_> = measure
+            .body
+            .into_iter()
+            .filter_map(|(timestamp, values)| {
+                let time = DateTime::from_timestamp(timestamp.parse().ok()?, 0)?;
+                Some((time, values))
+            })
+            .collect();
+        measures.sort_by_key(|(time, _)| *time);
+
+        Ok(measures)
     }
 }
 
+#[derive(Deserialize, Debug)]
+struct MeasureEnvelope {
+    body: HashMap<String, Vec<Option<f32>>>,
+}
+
+fn column(measures: &[(DateTime<Utc>, Vec<Option<f32>>)], index: usize) -> Vec<Measurement> {
+    measures
+        .iter()
+        .filter_map(|(time, values)| {
+            Some(Measurement {
+                time: *time,
+                value: (*values.get(index)?)?,
+            })
+        })
+        .collect()
+}
+
+fn convert(data_type: &str, dashboard_data: &Value) -> Result<Option<WeatherData>, anyhow::Error> {
+    Ok(match data_type {
+        "Wind" => Some(WeatherData::Wind(dashboard_data.try_into()?)),
+        "Temperature" => Some(WeatherData::Temperature(dashboard_data.try_into()?)),
+        "Humidity" => Some(WeatherData::Humidity(dashboard_data.try_into()?)),
+        "Rain" => Some(WeatherData::Rain(dashboard_data.try_into()?)),
+        "CO2" => Some(WeatherData::Co2(dashboard_data.try_into()?)),
+        "Noise" => Some(WeatherData::Noise(dashboard_data.try_into()?)),
+        "Pressure" => Some(WeatherData::Pressure(dashboard_data.try_into()?)),
+        _ => None,
+    })
+}
+
 #[derive(Debug, Clone)]
 pub enum WeatherData {
     Wind(Wind),
@@ -221,9 +679,11 @@ pub struct Wind {
     pub gust_angle: i16,
 }
 
-impl From<&Value> for Wind {
-    fn from(value: &Value) -> Self {
-        serde_json::from_value(value.clone()).unwrap()
+impl TryFrom<&Value> for Wind {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value.clone())
     }
 }
 
@@ -236,9 +696,11 @@ pub struct Temperature {
     pub temp_trend: Option<Trend>,
 }
 
-impl From<&Value> for Temperature {
-    fn from(value: &Value) -> Self {
-        serde_json::from_value(value.clone()).unwrap()
+impl TryFrom<&Value> for Temperature {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value.clone())
     }
 }
 
@@ -248,65 +710,152 @@ pub struct Humidity {
     pub humidity: f32,
 }
 
-impl From<&Value> for Humidity {
-    fn from(value: &Value) -> Self {
-        serde_json::from_value(value.clone()).unwrap()
+impl TryFrom<&Value> for Humidity {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value.clone())
     }
 }
 
-#[allow(unused)]
+/// Millimeters; `rain` covers the most recent five minutes.
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct Rain {
     #[serde(rename = "Rain")]
-    rain: f32,
-    sum_rain_1: f32,
-    sum_rain_24: f32,
+    pub rain: f32,
+    pub sum_rain_1: f32,
+    pub sum_rain_24: f32,
+}
+
+impl Rain {
+    pub fn is_raining(&self) -> bool {
+        self.rain > 0.0
+    }
 }
 
-impl From<&Value> for Rain {
-    fn from(value: &Value) -> Self {
-        serde_json::from_value(value.clone()).unwrap()
+impl TryFrom<&Value> for Rain {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value.clone())
     }
 }
 
 #[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
 pub struct Noise {
     #[serde(rename = "Noise")]
-    noise: u16,
+    pub noise: u16,
 }
 
-impl From<&Value> for Noise {
-    fn from(value: &Value) -> Self {
-        serde_json::from_value(value.clone()).unwrap()
+impl TryFrom<&Value> for Noise {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value.clone())
     }
 }
 
 #[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
 pub struct Co2 {
     #[serde(rename = "CO2")]
-    co2: u16,
+    pub co2: u16,
 }
 
-impl From<&Value> for Co2 {
-    fn from(value: &Value) -> Self {
-        serde_json::from_value(value.clone()).unwrap()
+impl Co2 {
+    /// Thresholds as used by the Netatmo app, in ppm.
+    pub fn level(&self) -> Co2Level {
+        if self.co2 < 1000 {
+            Co2Level::Good
+        } else if self.co2 < 2000 {
+            Co2Level::Ventilate
+        } else {
+            Co2Level::Bad
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Co2Level {
+    Good,
+    Ventilate,
+    Bad,
+}
+
+impl Co2Level {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Co2Level::Good => "good",
+            Co2Level::Ventilate => "ventilate",
+            Co2Level::Bad => "bad",
+        }
+    }
+}
+
+impl TryFrom<&Value> for Co2 {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value.clone())
     }
 }
 
 #[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
 pub struct Pressure {
+    /// Sea-level pressure, in mbar.
     #[serde(rename = "Pressure")]
-    pressure: f32,
+    pub pressure: f32,
     #[serde(rename = "AbsolutePressure")]
-    absolute_pressure: f32,
+    pub absolute_pressure: f32,
+    pub pressure_trend: Option<Trend>,
 }
 
-impl From<&Value> for Pressure {
-    fn from(value: &Value) -> Self {
-        serde_json::from_value(value.clone()).unwrap()
+impl TryFrom<&Value> for Pressure {
+    type Error = serde_json::Error;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value.clone())
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Measurement {
+    pub time: DateTime<Utc>,
+    pub value: f32,
+}
+
+/// The last day of readings at 30 minute scale, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct History {
+    pub temperature: Vec<Measurement>,
+    pub humidity: Vec<Measurement>,
+    pub pressure: Vec<Measurement>,
+}
+
+/// A drop of this many mbar within [`STORM_WINDOW_HOURS`] suggests a storm.
+const STORM_DROP: f32 = 3.0;
+const STORM_WINDOW_HOURS: i64 = 3;
+
+/// Whether pressure has fallen sharply over the last few hours. Needs
+/// samples spanning at least two thirds of the window to say anything.
+pub fn storm_incoming(history: &[Measurement], now: DateTime<Utc>) -> bool {
+    let since = now - Duration::hours(STORM_WINDOW_HOURS);
+    let recent: Vec<_> = history.iter().filter(|e| e.time >= since).collect();
+
+    let (Some(first), Some(last)) = (
+        recent.iter().min_by_key(|e| e.time),
+        recent.iter().max_by_key(|e| e.time),
+    ) else {
+        return false;
+    };
+
+    if last.time - first.time < Duration::minutes(STORM_WINDOW_HOURS * 40) {
+        return false;
+    }
+
+    let peak = recent.iter().map(|e| e.value).fold(f32::MIN, f32::max);
+    peak - last.value >= STORM_DROP
+}
+
 #[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Trend {
@@ -314,3 +863,44 @@ pub enum Trend {
     Down,
     Stable,
 }
+
+#[cfg(test)]
+mod test {
+    use crate::netatmo::{convert, storm_incoming, Measurement, WeatherData};
+    use chrono::{Duration, TimeZone, Utc};
+    use serde_json::json;
+
+    #[test]
+    fn convert_dead_module() {
+        let dashboard_data = json!({ "time_utc": 1760870000, "GustStrength": 3 });
+        assert!(convert("Wind", &dashboard_data).is_err());
+
+        let dashboard_data = json!({ "Temperature": 12.5, "min_temp": 9.1 });
+        assert!(matches!(
+            convert("Temperature", &dashboard_data),
+            Ok(Some(WeatherData::Temperature(_)))
+        ));
+        assert!(matches!(convert("Health", &dashboard_data), Ok(None)));
+    }
+
+    #[test]
+    fn storm() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+        let history = |pressures: &[f32]| -> Vec<Measurement> {
+            pressures
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, pressure)| Measurement {
+                    time: now - Duration::minutes(30 * i as i64),
+                    value: *pressure,
+                })
+                .collect()
+        };
+
+        assert!(storm_incoming(&history(&[1012.0, 1011.2, 1010.4, 1009.6, 1009.0, 1008.5]), now));
+        assert!(!storm_incoming(&history(&[1012.0, 1011.8, 1011.5, 1011.3, 1011.0, 1010.9]), now));
+        // not enough history to judge.
+        assert!(!storm_incoming(&history(&[1012.0, 1008.0]), now));
+    }
+}
@@ -14,9 +14,14 @@ pub struct Body {
 pub struct Device {
     pub _id: String,
     pub station_name: String,
+    pub module_name: Option<String>,
     #[serde(rename = "type")]
     pub ty: String,
+    #[serde(default)]
     pub data_type: Vec<String>,
-    pub dashboard_data: Value,
+    pub dashboard_data: Option<Value>,
+    #[serde(default)]
     pub modules: Vec<Map<String, Value>>,
+    pub reachable: Option<bool>,
+    pub wifi_status: Option<u16>,
 }
@@ -0,0 +1,360 @@
+use crate::forecast::{Alert, Condition, DailyForecast, Details, HourlyForecast, Severity, Temperature, Wind};
+use crate::state::state;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::cell::RefCell;
+
+const POINTS_URL: &str = "https://api.weather.gov/points";
+const ALERTS_URL: &str = "https://api.weather.gov/alerts/active";
+
+
+/// api.weather.gov turns away requests without a User-Agent.
+const USER_AGENT: &str = concat!("lattitude/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Deserialize, Debug, Clone)]
+struct Points {
+    properties: PointsProperties,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PointsProperties {
+    forecast: String,
+    forecast_hourly: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Forecast {
+    properties: ForecastProperties,
+}
+
+#[derive(Deserialize, Debug)]
+struct ForecastProperties {
+    periods: Vec<Period>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Period {
+    start_time: DateTime<FixedOffset>,
+    is_daytime: bool,
+    temperature: f32,
+    probability_of_precipitation: Option<Probability>,
+    wind_speed: Option<String>,
+    wind_direction: Option<String>,
+    icon: Option<String>,
+    short_forecast: String,
+    detailed_forecast: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Probability {
+    value: Option<u8>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Alerts {
+    features: Vec<AlertFeature>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlertFeature {
+    properties: AlertProperties,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlertProperties {
+    event: String,
+    headline: Option<String>,
+    severity: String,
+    onset: Option<DateTime<FixedOffset>>,
+    expires: Option<DateTime<FixedOffset>>,
+    ends: Option<DateTime<FixedOffset>>,
+}
+
+/// The US National Weather Service. Only covers US locations; the
+/// gridpoint forecast URLs are looked up once from `location`.
+pub struct NwsClient {
+    points: RefCell<Option<PointsProperties>>,
+}
+
+impl NwsClient {
+    pub fn new() -> Self {
+        Self {
+            points: RefCell::new(None),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str, query: &[(&str, String)]) -> Result<T, anyhow::Error> {
+        Ok(reqwest::Client::new()
+            .get(url)
+            .query(query)
+            .header("User-Agent", USER_AGENT)
+            .header("Accept", "application/geo+json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn points(&self) -> Result<PointsProperties, anyhow::Error> {
+        if let Some(points) = &*self.points.borrow() {
+            return Ok(points.clone());
+        }
+
+        let location = state().location;
+        // the API redirects anything more precise than four decimals.
+        let url = format!("{}/{:.4},{:.4}", POINTS_URL, location.lat, location.lon);
+        let points: Points = self.get(&url, &[]).await?;
+
+        self.points.borrow_mut().replace(points.properties.clone());
+
+        Ok(points.properties)
+    }
+
+    pub async fn daily_forecast(&self) -> Result<Vec<DailyForecast>, anyhow::Error> {
+        let points = self.points().await?;
+        let forecast: Forecast = self.get(&points.forecast, &[]).await?;
+        Ok(daily(forecast.properties.periods))
+    }
+
+    pub async fn hourly_forecasts(&self) -> Result<Vec<HourlyForecast>, anyhow::Error> {
+        let points = self.points().await?;
+        let forecast: Forecast = self.get(&points.forecast_hourly, &[]).await?;
+        Ok(hourly(
+            forecast.properties.periods,
+            Utc::now(),
+            state().display.hourly_hours,
+        ))
+    }
+
+    /// Active watches, warnings and advisories for `location`.
+    pub async fn alerts(&self) -> Result<Vec<Alert>, anyhow::Error> {
+        let location = state().location;
+        let envelope: Alerts = self
+            .get(
+                ALERTS_URL,
+                &[("point", format!("{:.4},{:.4}", location.lat, location.lon))],
+            )
+            .await?;
+        Ok(alerts(envelope))
+    }
+}
+
+/// Folds the day and night periods into one forecast per date. When the
+/// first period is already tonight, that date has only the night to go on.
+/// Days are the forecast location's, as NWS reports them in its own offset,
+/// whatever time zone lattitude runs in.
+fn daily(periods: Vec<Period>) -> Vec<DailyForecast> {
+    let mut days: Vec<(NaiveDate, Option<Period>, Option<Period>)> = vec![];
+    for period in periods {
+        let date = period.start_time.date_naive();
+        let index = match days.iter().position(|(e, _, _)| *e == date) {
+            Some(index) => index,
+            None => {
+                days.push((date, None, None));
+                days.len() - 1
+            }
+        };
+        if period.is_daytime {
+            days[index].1 = Some(period);
+        } else {
+            days[index].2 = Some(period);
+        }
+    }
+
+    days.into_iter()
+        .filter_map(|(date, day, night)| {
+            let date = Local
+                .from_local_datetime(&date.and_time(NaiveTime::MIN))
+                .earliest()?;
+            let maximum = day.as_ref().or(night.as_ref())?.temperature;
+            let minimum = night.as_ref().or(day.as_ref())?.temperature;
+            let night = night.map(details);
+            let day = day.map(details).or(night.clone())?;
+
+            Some(DailyForecast {
+                date,
+                sun: None,
+                moon: None,
+                temperature: Temperature { minimum, maximum },
+                day,
+                night: night.unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn hourly(periods: Vec<Period>, now: DateTime<Utc>, hours: usize) -> Vec<HourlyForecast> {
+    periods
+        .into_iter()
+        .filter(|e| now - e.start_time.with_timezone(&Utc) < chrono::Duration::hours(1))
+        .take(hours)
+        .map(|e| HourlyForecast {
+            date_time: e.start_time.with_timezone(&Local),
+            temperature: e.temperature,
+            precipitation_probability: precipitation_probability(&e),
+            condition: e.icon.as_deref().map(condition).unwrap_or_default(),
+            phrase: e.short_forecast,
+        })
+        .collect()
+}
+
+fn details(period: Period) -> Details {
+    let speed = period
+        .wind_speed
+        .as_deref()
+        .and_then(|e| e.split_whitespace().filter_map(|e| e.parse::<f32>().ok()).next_back());
+    let degrees = period.wind_direction.as_deref().and_then(Wind::from_compass);
+
+    Details {
+        condition: period.icon.as_deref().map(condition).unwrap_or_default(),
+        precipitation_probability: precipitation_probability(&period),
+        wind: speed.zip(degrees).map(|(speed, degrees)| Wind { speed, degrees }),
+        phrase: period.detailed_forecast,
+        ..Default::default()
+    }
+}
+
+fn precipitation_probability(period: &Period) -> u8 {
+    period
+        .probability_of_precipitation
+        .as_ref()
+        .and_then(|e| e.value)
+        .unwrap_or_default()
+}
+
+/// Reads the condition from an icon URL such as
+/// `.../icons/land/night/tsra_sct,40/bkn?size=medium`, using the first of
+/// the two conditions when the period changes partway through.
+fn condition(icon: &str) -> Condition {
+    let path = icon.split('?').next().unwrap_or_default();
+    let mut segments = path.split('/').skip_while(|e| *e != "day" && *e != "night");
+    let night = segments.next() == Some("night");
+    let code = segments.next().and_then(|e| e.split(',').next()).unwrap_or_default();
+
+    let condition = match code {
+        "skc" | "few" | "haze" => Condition::Clear,
+        "hot" => Condition::Hot,
+        "cold" => Condition::Cold,
+        "sct" => Condition::PartlyCloudy,
+        "bkn" => Condition::MostlyCloudy,
+        "ovc" => Condition::Cloudy,
+        "wind_skc" | "wind_few" | "wind_sct" | "wind_bkn" | "wind_ovc" => Condition::Windy,
+        "fog" | "smoke" | "dust" => Condition::Fog,
+        "rain_showers" | "rain_showers_hi" => Condition::Showers,
+        "rain" => Condition::Rain,
+        "tsra" | "tornado" | "hurricane" | "tropical_storm" => Condition::Thunderstorms,
+        "tsra_sct" | "tsra_hi" => Condition::ScatteredThunderstorms,
+        "snow" | "blizzard" => Condition::Snow,
+        "fzra" | "rain_fzra" | "snow_fzra" => Condition::Ice,
+        "sleet" | "rain_sleet" | "snow_sleet" | "rain_snow" => Condition::Sleet,
+        _ => Condition::Unknown,
+    };
+
+    if night {
+        condition.at_night()
+    } else {
+        condition
+    }
+}
+
+fn alerts(alerts: Alerts) -> Vec<Alert> {
+    let mut alerts: Vec<_> = alerts
+        .features
+        .into_iter()
+        .map(|e| e.properties)
+        .map(|e| Alert {
+            severity: match e.severity.as_str() {
+                "Extreme" => Severity::Extreme,
+                "Severe" => Severity::Severe,
+                "Moderate" => Severity::Moderate,
+                "Minor" => Severity::Minor,
+                _ => Severity::Unknown,
+            },
+            event: e.event,
+            headline: e.headline,
+            onset: e.onset.map(|e| e.with_timezone(&Local)),
+            // `ends` is when the hazard is over; `expires` only when this
+            // message will be superseded.
+            expires: e.ends.or(e.expires).map(|e| e.with_timezone(&Local)),
+        })
+        .collect();
+    alerts.sort_by_key(|e| std::cmp::Reverse(e.severity));
+    alerts
+}
+
+#[cfg(test)]
+mod test {
+    use crate::forecast::{Condition, Severity};
+    use crate::nws::{alerts, daily, hourly, Forecast, Points};
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    #[test]
+    fn points() {
+        let points: Points =
+            serde_json::from_str(include_str!("../../fixtures/nws/points.json")).unwrap();
+        assert_eq!(
+            points.properties.forecast_hourly,
+            "https://api.weather.gov/gridpoints/RNK/52,39/forecast/hourly"
+        );
+    }
+
+    #[test]
+    fn daily_forecast() {
+        let forecast: Forecast =
+            serde_json::from_str(include_str!("../../fixtures/nws/forecast.json")).unwrap();
+        let forecast = daily(forecast.properties.periods);
+
+        assert_eq!(forecast.len(), 3);
+        assert_eq!(
+            forecast[0].date.date_naive(),
+            NaiveDate::from_ymd_opt(2024, 6, 15).unwrap()
+        );
+
+        // tonight only.
+        assert_eq!(forecast[0].temperature.maximum, 58.0);
+        assert_eq!(forecast[0].day.condition, Condition::ScatteredThunderstormsNight);
+        assert_eq!(forecast[0].day.precipitation_probability, 40);
+
+        assert_eq!(forecast[1].temperature.maximum, 81.0);
+        assert_eq!(forecast[1].temperature.minimum, 55.0);
+        assert_eq!(forecast[1].day.condition, Condition::Clear);
+        assert_eq!(forecast[1].day.precipitation_probability, 0);
+        assert_eq!(forecast[1].day.wind.as_ref().unwrap().speed, 10.0);
+        assert_eq!(forecast[1].day.wind.as_ref().unwrap().degrees, 270);
+        assert_eq!(forecast[1].night.condition, Condition::ClearNight);
+
+        assert_eq!(forecast[2].day.condition, Condition::Showers);
+    }
+
+    #[test]
+    fn hourly_forecast() {
+        let forecast: Forecast =
+            serde_json::from_str(include_str!("../../fixtures/nws/forecast_hourly.json")).unwrap();
+        let now = DateTime::parse_from_rfc3339("2024-06-15T18:20:00-04:00").unwrap().with_timezone(&Utc);
+        let forecast = hourly(forecast.properties.periods, now, 12);
+
+        // the 5pm period is over.
+        assert_eq!(forecast.len(), 2);
+        assert_eq!(forecast[0].temperature, 76.0);
+        assert_eq!(forecast[0].precipitation_probability, 40);
+        assert_eq!(forecast[1].condition, Condition::MostlyCloudyNight);
+    }
+
+    #[test]
+    fn active_alerts() {
+        let alerts = alerts(
+            serde_json::from_str(include_str!("../../fixtures/nws/alerts.json")).unwrap(),
+        );
+
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].event, "Severe Thunderstorm Warning");
+        assert_eq!(alerts[0].severity, Severity::Severe);
+        assert!(alerts[0].expires.is_some());
+        assert_eq!(alerts[1].severity, Severity::Unknown);
+        assert!(alerts[1].headline.is_none());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::forecast::{Condition, DailyForecast, Details, HourlyForecast, Sun, Temperature, Wind};
+use crate::state::state;
+use chrono::{DateTime, Local, TimeZone};
+use serde::Deserialize;
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+const DAILY_FIELDS: &str = "weather_code,temperature_2m_max,temperature_2m_min,sunrise,sunset,\
+precipitation_probability_max,rain_sum,showers_sum,snowfall_sum,wind_speed_10m_max,\
+wind_gusts_10m_max,wind_direction_10m_dominant";
+const HOURLY_FIELDS: &str = "temperature_2m,precipitation_probability,weather_code,is_day";
+
+const FORECAST_DAYS: usize = 5;
+
+/// Open-Meteo needs no key. Each series comes back as a column of values,
+/// one per entry in `time`.
+#[derive(Deserialize, Debug)]
+struct Envelope {
+    daily: Option<Daily>,
+    hourly: Option<Hourly>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Daily {
+    time: Vec<i64>,
+    weather_code: Vec<Option<u8>>,
+    temperature_2m_max: Vec<Option<f32>>,
+    temperature_2m_min: Vec<Option<f32>>,
+    sunrise: Vec<i64>,
+    sunset: Vec<i64>,
+    precipitation_probability_max: Vec<Option<u8>>,
+    rain_sum: Vec<Option<f32>>,
+    showers_sum: Vec<Option<f32>>,
+    snowfall_sum: Vec<Option<f32>>,
+    wind_speed_10m_max: Vec<Option<f32>>,
+    wind_gusts_10m_max: Vec<Option<f32>>,
+    wind_direction_10m_dominant: Vec<Option<u16>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Hourly {
+    time: Vec<i64>,
+    temperature_2m: Vec<Option<f32>>,
+    precipitation_probability: Vec<Option<u8>>,
+    weather_code: Vec<Option<u8>>,
+    is_day: Vec<Option<u8>>,
+}
+
+pub struct OpenMeteoClient {}
+
+impl OpenMeteoClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn get(&self, series: (&str, &str)) -> Result<Envelope, anyhow::Error> {
+        let location = state().location;
+
+        let envelope = reqwest::Client::new()
+            .get(FORECAST_URL)
+            .query(&[
+                ("latitude", location.lat.to_string()),
+                ("longitude", location.lon.to_string()),
+                (series.0, series.1.to_string()),
+                ("forecast_days", FORECAST_DAYS.to_string()),
+                ("forecast_hours", state().display.hourly_hours.to_string()),
+                ("temperature_unit", "fahrenheit".to_string()),
+                ("wind_speed_unit", "mph".to_string()),
+                ("precipitation_unit", "inch".to_string()),
+                ("timeformat", "unixtime".to_string()),
+                ("timezone", "auto".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(envelope)
+    }
+
+    pub async fn daily_forecast(&self) -> Result<Vec<DailyForecast>, anyhow::Error> {
+        let envelope = self.get(("daily", DAILY_FIELDS)).await?;
+        Ok(envelope.daily.map(daily).unwrap_or_default())
+    }
+
+    pub async fn hourly_forecasts(&self) -> Result<Vec<HourlyForecast>, anyhow::Error> {
+        let envelope = self.get(("hourly", HOURLY_FIELDS)).await?;
+        Ok(envelope.hourly.map(hourly).unwrap_or_default())
+    }
+}
+
+fn local(timestamp: i64) -> Option<DateTime<Local>> {
+    Local.timestamp_opt(timestamp, 0).single()
+}
+
+fn value<T: Copy>(column: &[Option<T>], i: usize) -> Option<T> {
+    column.get(i).copied().flatten()
+}
+
+/// Open-Meteo has no day/night split, so everything lands in `day`.
+/// Temperatures are rounded to whole degrees, as AccuWeather reports them.
+fn daily(daily: Daily) -> Vec<DailyForecast> {
+    (0..daily.time.len())
+        .filter_map(|i| {
+            let condition = value(&daily.weather_code, i).map(condition).unwrap_or_default();
+            let wind = |speed: &[Option<f32>]| {
+                Some(Wind {
+                    speed: value(speed, i)?,
+                    degrees: value(&daily.wind_direction_10m_dominant, i).unwrap_or_default(),
+                })
+            };
+
+            Some(DailyForecast {
+                date: local(daily.time[i])?,
+                sun: Some(Sun {
+                    rise: local(*daily.sunrise.get(i)?)?,
+                    set: local(*daily.sunset.get(i)?)?,
+                    dawn: None,
+                    dusk: None,
+                }),
+                moon: None,
+                temperature: Temperature {
+                    minimum: value(&daily.temperature_2m_min, i)?.round(),
+                    maximum: value(&daily.temperature_2m_max, i)?.round(),
+                },
+                day: Details {
+                    condition,
+                    phrase: condition.label().to_string(),
+                    precipitation_probability: value(&daily.precipitation_probability_max, i)
+                        .unwrap_or_default(),
+                    rain: value(&daily.rain_sum, i).unwrap_or_default()
+                        + value(&daily.showers_sum, i).unwrap_or_default(),
+                    snow: value(&daily.snowfall_sum, i).unwrap_or_default(),
+                    ice: 0.0,
+                    wind: wind(&daily.wind_speed_10m_max),
+                    wind_gust: wind(&daily.wind_gusts_10m_max),
+                },
+                night: Details::default(),
+            })
+        })
+        .collect()
+}
+
+fn hourly(hourly: Hourly) -> Vec<HourlyForecast> {
+    (0..hourly.time.len())
+        .filter_map(|i| {
+            let mut condition = value(&hourly.weather_code, i).map(condition).unwrap_or_default();
+            if value(&hourly.is_day, i) == Some(0) {
+                condition = condition.at_night();
+            }
+            Some(HourlyForecast {
+                date_time: local(hourly.time[i])?,
+                temperature: value(&hourly.temperature_2m, i)?.round(),
+                precipitation_probability: value(&hourly.precipitation_probability, i)
+                    .unwrap_or_default(),
+                condition,
+                phrase: condition.label().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Maps WMO weather interpretation codes, as used by Open-Meteo.
+fn condition(code: u8) -> Condition {
+    match code {
+        0 | 1 => Condition::Clear,
+        2 => Condition::PartlyCloudy,
+        3 => Condition::Cloudy,
+        45 | 48 => Condition::Fog,
+        51 | 53 | 55 | 80 | 81 => Condition::Showers,
+        61 | 63 | 65 | 82 => Condition::Rain,
+        56 | 57 | 66 | 67 => Condition::Ice,
+        71 | 77 | 85 => Condition::Flurries,
+        73 | 75 | 86 => Condition::Snow,
+        95 | 96 | 99 => Condition::Thunderstorms,
+        _ => Condition::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::forecast::Condition;
+    use crate::openmeteo::{daily, Envelope};
+
+    #[test]
+    fn daily_columns() {
+        let json = r#"{
+            "daily": {
+                "time": [1718424000, 1718510400],
+                "weather_code": [61, null],
+                "temperature_2m_max": [81.2, 79.0],
+                "temperature_2m_min": [60.1, null],
+                "sunrise": [1718444000, 1718530400],
+                "sunset": [1718497000, 1718583400],
+                "precipitation_probability_max": [70, 10],
+                "rain_sum": [0.25, 0.0],
+                "showers_sum": [0.05, 0.0],
+                "snowfall_sum": [0.0, 0.0],
+                "wind_speed_10m_max": [12.0, 8.0],
+                "wind_gusts_10m_max": [20.0, null],
+                "wind_direction_10m_dominant": [225, 180]
+            }
+        }"#;
+
+        let envelope: Envelope = serde_json::from_str(json).unwrap();
+        let forecast = daily(envelope.daily.unwrap());
+
+        // the second day has no minimum temperature, so it is dropped.
+        assert_eq!(forecast.len(), 1);
+        assert_eq!(forecast[0].day.condition, Condition::Rain);
+        assert_eq!(forecast[0].day.precipitation_probability, 70);
+        assert!((forecast[0].day.rain - 0.30).abs() < 0.001);
+        assert_eq!(forecast[0].day.wind.as_ref().unwrap().degrees, 225);
+        assert!(forecast[0].moon.is_none());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::purple::{channel_average, mean, Aqi, HistoryReading};
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::Deserialize;
+use std::cell::RefCell;
+
+/// Readings older than this are dropped; it bounds the longest average.
+const RETAIN_HOURS: i64 = 24;
+
+/// The `/json` document served by the sensor itself. Single-laser sensors
+/// leave out the `_b` channel.
+#[derive(Deserialize, Debug)]
+struct LocalSensor {
+    current_humidity: Option<f64>,
+    pm2_5_atm: f64,
+    pm2_5_atm_b: Option<f64>,
+    pm2_5_cf_1: Option<f64>,
+    pm2_5_cf_1_b: Option<f64>,
+}
+
+/// Polls a sensor on the local network. The sensor only reports the
+/// present, so the readings are kept to work out the rolling averages the
+/// API would otherwise provide.
+pub struct LocalClient {
+    address: String,
+    readings: RefCell<Vec<HistoryReading>>,
+}
+
+impl LocalClient {
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            readings: RefCell::new(vec![]),
+        }
+    }
+
+    pub async fn get_aqi(&self) -> Result<Aqi, anyhow::Error> {
+        let url = if self.address.starts_with("http") {
+            format!("{}/json", self.address.trim_end_matches('/'))
+        } else {
+            format!("http://{}/json", self.address)
+        };
+
+        let sensor: LocalSensor = reqwest::Client::new()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let pm2_5 = match sensor.pm2_5_atm_b {
+            Some(b) => channel_average(Some(sensor.pm2_5_atm), Some(b))
+                .ok_or(anyhow!("purple: sensor channels disagree"))?,
+            None => sensor.pm2_5_atm,
+        };
+        let pm2_5_cf_1 = match sensor.pm2_5_cf_1_b {
+            Some(b) => channel_average(sensor.pm2_5_cf_1, Some(b)),
+            None => sensor.pm2_5_cf_1,
+        };
+
+        let now = Utc::now();
+        let mut readings = self.readings.borrow_mut();
+        readings.push(HistoryReading {
+            time: now,
+            pm2_5,
+            pm2_5_cf_1,
+            humidity: sensor.current_humidity,
+        });
+        readings.retain(|e| now - e.time < Duration::hours(RETAIN_HOURS));
+
+        rolling(&readings, now).ok_or(anyhow!("purple: no readings"))
+    }
+
+    pub fn history(&self) -> Vec<HistoryReading> {
+        hourly(&self.readings.borrow())
+    }
+}
+
+/// The latest reading, with averages over whatever part of each window has
+/// been polled so far.
+fn rolling(readings: &[HistoryReading], now: DateTime<Utc>) -> Option<Aqi> {
+    let latest = readings.last()?;
+    let average = |hours| {
+        mean(
+            readings
+                .iter()
+                .filter(|e| now - e.time < Duration::hours(hours))
+                .map(|e| e.pm2_5),
+        )
+        .unwrap_or(latest.pm2_5)
+    };
+
+    Some(Aqi {
+        current: latest.pm2_5,
+        pm2_5_cf_1: latest.pm2_5_cf_1,
+        humidity: latest.humidity,
+        one_hour: average(1),
+        six_hour: average(6),
+        twenty_four_hour: average(24),
+        sensors: 1,
+        confidence: None,
+    })
+}
+
+/// Averages readings into one per clock hour, oldest first.
+fn hourly(readings: &[HistoryReading]) -> Vec<HistoryReading> {
+    let mut hours: Vec<(DateTime<Utc>, Vec<&HistoryReading>)> = vec![];
+    for reading in readings {
+        let Ok(hour) = reading.time.duration_trunc(Duration::hours(1)) else {
+            continue;
+        };
+        match hours.last_mut() {
+            Some((last, bucket)) if *last == hour => bucket.push(reading),
+            _ => hours.push((hour, vec![reading])),
+        }
+    }
+
+    hours
+        .into_iter()
+        .filter_map(|(time, bucket)| {
+            Some(HistoryReading {
+                time,
+                pm2_5: mean(bucket.iter().map(|e| e.pm2_5))?,
+                pm2_5_cf_1: mean(bucket.iter().filter_map(|e| e.pm2_5_cf_1)),
+                humidity: mean(bucket.iter().filter_map(|e| e.humidity)),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::purple::local::{hourly, rolling};
+    use crate::purple::HistoryReading;
+    use chrono::{DateTime, Duration};
+
+    #[test]
+    fn rolling_averages() {
+        let start = DateTime::from_timestamp(1_700_000_000 / 3600 * 3600, 0).unwrap();
+        let readings: Vec<_> = (0..6)
+            .map(|i| HistoryReading {
+                time: start + Duration::minutes(i * 30),
+                pm2_5: (i * 2) as f64,
+                pm2_5_cf_1: None,
+                humidity: None,
+            })
+            .collect();
+
+        let now = start + Duration::minutes(150);
+        let aqi = rolling(&readings, now).unwrap();
+        assert_eq!(aqi.current, 10.0);
+        // readings at 2:00 and 2:30.
+        assert_eq!(aqi.one_hour, 9.0);
+        assert_eq!(aqi.six_hour, 5.0);
+
+        let hours = hourly(&readings);
+        assert_eq!(hours.len(), 3);
+        assert_eq!(hours[0].time, start);
+        assert_eq!(hours[0].pm2_5, 1.0);
+        assert_eq!(hours[2].pm2_5, 9.0);
+    }
+}
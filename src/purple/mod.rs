@@ -1,45 +1,333 @@
-use clap::Parser;
-use crate::accuweather::daily_forecast::Snow;
-use crate::purple::purple_data::Envelope;
-use crate::state::state;
+use crate::aqi::{epa_correction, pm2_5_to_aqi, AqiIndex};
+use crate::netatmo::Trend;
+use crate::purple::purple_data::{Envelope, SensorReading};
+use crate::purple::local::LocalClient;
+use crate::state::{state, PurpleApiState, PurpleState};
+use anyhow::anyhow;
+use chrono::{Duration, Utc};
 
+mod local;
 mod purple_data;
 
+pub use purple_data::HistoryReading;
+
 const GET_SENSOR_DATA_URL: &str = "https://api.purpleair.com/v1/sensors";
 
-pub struct PurpleClient {}
+const FIELDS: &str = "sensor_index,humidity,confidence,pm2.5_a,pm2.5_b,pm2.5_cf_1_a,pm2.5_cf_1_b,\
+pm2.5_60minute,pm2.5_6hour,pm2.5_24hour";
+
+const HISTORY_FIELDS: &str = "humidity,pm2.5_atm,pm2.5_cf_1";
+const HISTORY_HOURS: i64 = 24;
+
+const LOCAL_CADENCE_MINUTES: i64 = 2;
+
+/// The 1h average has to move at least this many µg/m³, and this fraction
+/// of the 6h average, before the air counts as rising or falling.
+const TREND_MIN_CHANGE: f64 = 2.0;
+const TREND_MIN_FRACTION: f64 = 0.15;
+
+/// Channels disagreeing by more than this many µg/m³ *and* by more than
+/// [`MAX_CHANNEL_DIFFERENCE_PERCENT`] are discarded, per the US EPA QA.
+const MAX_CHANNEL_DIFFERENCE: f64 = 5.0;
+const MAX_CHANNEL_DIFFERENCE_PERCENT: f64 = 70.0;
+
+/// Sensors further than this many scaled median absolute deviations from
+/// the median are dropped before averaging.
+const OUTLIER_DEVIATIONS: f64 = 3.0;
+/// Keeps a tight cluster of sensors from rejecting everything else.
+const MIN_DEVIATION: f64 = 2.0;
+
+/// Reads PurpleAir through api.purpleair.com or straight from a sensor on
+/// the local network, depending on how `[purple]` is configured.
+pub enum PurpleClient {
+    Api(PurpleApiState),
+    Local(LocalClient),
+}
 
 impl PurpleClient {
     pub fn new() -> Self {
-        Self {}
+        match state().purple {
+            PurpleState::Api(api) => PurpleClient::Api(api),
+            PurpleState::Local(local) => PurpleClient::Local(LocalClient::new(local.address)),
+        }
+    }
+
+    /// API calls cost points, so they are spaced out; a local sensor is
+    /// polled often enough to build its own averages.
+    pub fn cadence(&self) -> Duration {
+        match self {
+            PurpleClient::Api(_) => Duration::hours(2),
+            PurpleClient::Local(_) => Duration::minutes(LOCAL_CADENCE_MINUTES),
+        }
     }
 
     pub async fn get_aqi(&self) -> Result<Aqi, anyhow::Error> {
-        let state = state().purple;
-
-        let url = format!("{}/{}", GET_SENSOR_DATA_URL, state.sensor_index);
-        let response = reqwest::Client::new()
-            .get(url)
-            .query(&[("fields", "pm2.5,pm2.5_60minute,pm2.5_6hour,pm2.5_24hour")])
-            .header("X-API-KEY", state.api_key)
-            .send()
-            .await?;
-
-        let data: Envelope = response.json().await?;
-
-        Ok(Aqi {
-            current: data.sensor.pm2_5,
-            one_hour: data.sensor.stats.pm2_5_1hour,
-            six_hour: data.sensor.stats.pm2_5_6hour,
-            twenty_four_hour: data.sensor.stats.pm2_5_24hour,
+        match self {
+            PurpleClient::Api(api) => get_api_aqi(api).await,
+            PurpleClient::Local(local) => local.get_aqi().await,
+        }
+    }
+
+    /// Hourly averages for the last day.
+    pub async fn get_history(&self) -> Result<Vec<HistoryReading>, anyhow::Error> {
+        match self {
+            PurpleClient::Api(api) => get_api_history(api).await,
+            PurpleClient::Local(local) => Ok(local.history()),
+        }
+    }
+}
+
+async fn get_api_aqi(purple: &PurpleApiState) -> Result<Aqi, anyhow::Error> {
+    let location = state().location;
+
+    let mut query = vec![("fields", FIELDS.to_string())];
+
+    let sensors = configured_sensors(purple);
+
+    if let Some(radius_km) = purple.radius_km {
+        let lat_delta = radius_km / 111.0;
+        let lon_delta = radius_km / (111.0 * location.lat.to_radians().cos());
+        query.extend([
+            ("location_type", "0".to_string()),
+            ("nwlat", (location.lat + lat_delta).to_string()),
+            ("selat", (location.lat - lat_delta).to_string()),
+            ("nwlng", (location.lon - lon_delta).to_string()),
+            ("selng", (location.lon + lon_delta).to_string()),
+        ]);
+    } else if !sensors.is_empty() {
+        query.push(("show_only", sensors.join(",")));
+    } else {
+        return Err(anyhow!("purple: configure sensors or radius_km"));
+    }
+
+    let response = reqwest::Client::new()
+        .get(GET_SENSOR_DATA_URL)
+        .query(&query)
+        .header("X-API-KEY", &purple.api_key)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let data: Envelope = response.json().await?;
+
+    combine(&data.readings()).ok_or(anyhow!("purple: no healthy sensors"))
+}
+
+/// Uses the first configured sensor; the history endpoint only takes one
+/// sensor at a time.
+async fn get_api_history(purple: &PurpleApiState) -> Result<Vec<HistoryReading>, anyhow::Error> {
+    let sensor_index = configured_sensors(purple)
+        .into_iter()
+        .next()
+        .ok_or(anyhow!("purple: history needs a sensor_index"))?;
+
+    let start = Utc::now() - Duration::hours(HISTORY_HOURS);
+
+    let response = reqwest::Client::new()
+        .get(format!("{GET_SENSOR_DATA_URL}/{sensor_index}/history"))
+        .query(&[
+            ("fields", HISTORY_FIELDS.to_string()),
+            ("average", "60".to_string()),
+            ("start_timestamp", start.timestamp().to_string()),
+        ])
+        .header("X-API-KEY", &purple.api_key)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let data: Envelope = response.json().await?;
+
+    Ok(data.history())
+}
+
+fn configured_sensors(purple: &PurpleApiState) -> Vec<String> {
+    let mut sensors = purple.sensor_index.iter().cloned().collect::<Vec<_>>();
+    sensors.extend(purple.sensors.iter().cloned());
+    sensors
+}
+
+/// The US EPA correction when the sensor reported what it needs, otherwise
+/// the raw reading.
+fn corrected(pm2_5: f64, pm2_5_cf_1: Option<f64>, humidity: Option<f64>) -> f64 {
+    match (pm2_5_cf_1, humidity) {
+        (Some(pm2_5_cf_1), Some(humidity)) => epa_correction(pm2_5_cf_1, humidity),
+        _ => pm2_5,
+    }
+}
+
+/// Average of the two laser channels, or `None` when they disagree enough
+/// that one of them has likely failed.
+fn channel_average(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    let (a, b) = (a?, b?);
+    let difference = (a - b).abs();
+    let mean = (a + b) / 2.0;
+    let percent = if mean > 0.0 { difference / mean * 100.0 } else { 0.0 };
+
+    if difference > MAX_CHANNEL_DIFFERENCE && percent > MAX_CHANNEL_DIFFERENCE_PERCENT {
+        None
+    } else {
+        Some(mean)
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), e| (sum + e, count + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f64)
+    }
+}
+
+/// Averages the sensors whose channels agree, after rejecting outliers.
+fn combine(readings: &[SensorReading]) -> Option<Aqi> {
+    let healthy: Vec<_> = readings
+        .iter()
+        .filter_map(|reading| {
+            let pm2_5 = channel_average(reading.pm2_5_a, reading.pm2_5_b)?;
+            let pm2_5_cf_1 = channel_average(reading.pm2_5_cf_1_a, reading.pm2_5_cf_1_b);
+            Some((reading, pm2_5, pm2_5_cf_1))
         })
+        .collect();
+
+    let healthy = if healthy.len() >= 3 {
+        let mut values: Vec<_> = healthy.iter().map(|(_, pm2_5, _)| *pm2_5).collect();
+        let center = median(&mut values);
+        let mut deviations: Vec<_> = values.iter().map(|e| (e - center).abs()).collect();
+        // 1.4826 scales the MAD to a standard deviation for normal data.
+        let deviation = (median(&mut deviations) * 1.4826).max(MIN_DEVIATION);
+
+        healthy
+            .into_iter()
+            .filter(|(_, pm2_5, _)| (pm2_5 - center).abs() <= OUTLIER_DEVIATIONS * deviation)
+            .collect()
+    } else {
+        healthy
+    };
+
+    if healthy.is_empty() {
+        return None;
     }
+
+    Some(Aqi {
+        current: mean(healthy.iter().map(|(_, pm2_5, _)| *pm2_5))?,
+        pm2_5_cf_1: mean(healthy.iter().filter_map(|(_, _, cf_1)| *cf_1)),
+        humidity: mean(healthy.iter().filter_map(|(e, _, _)| e.humidity)),
+        one_hour: mean(healthy.iter().filter_map(|(e, _, _)| e.pm2_5_1hour)).unwrap_or_default(),
+        six_hour: mean(healthy.iter().filter_map(|(e, _, _)| e.pm2_5_6hour)).unwrap_or_default(),
+        twenty_four_hour: mean(healthy.iter().filter_map(|(e, _, _)| e.pm2_5_24hour))
+            .unwrap_or_default(),
+        sensors: healthy.len(),
+        confidence: mean(healthy.iter().filter_map(|(e, _, _)| e.confidence)),
+    })
 }
 
+/// PM2.5 readings in µg/m³, not index values; see [`Aqi::index`].
 #[derive(Debug, Clone, PartialEq)]
 pub struct Aqi {
     pub current: f64,
+    pub pm2_5_cf_1: Option<f64>,
+    /// Relative humidity inside the sensor housing.
+    pub humidity: Option<f64>,
     pub one_hour: f64,
     pub six_hour: f64,
     pub twenty_four_hour: f64,
-}
\ No newline at end of file
+    /// How many sensors made it into the average.
+    pub sensors: usize,
+    pub confidence: Option<f64>,
+}
+
+impl Aqi {
+    /// PM2.5 with the US EPA correction applied where possible.
+    pub fn corrected(&self) -> f64 {
+        corrected(self.current, self.pm2_5_cf_1, self.humidity)
+    }
+
+    pub fn index(&self) -> AqiIndex {
+        pm2_5_to_aqi(self.corrected())
+    }
+
+    /// Whether the last hour is running above or below the last six.
+    pub fn trend(&self) -> Trend {
+        let change = self.one_hour - self.six_hour;
+        if change.abs() < TREND_MIN_CHANGE.max(self.six_hour * TREND_MIN_FRACTION) {
+            Trend::Stable
+        } else if change > 0.0 {
+            Trend::Up
+        } else {
+            Trend::Down
+        }
+    }
+}
+
+impl HistoryReading {
+    pub fn index(&self) -> AqiIndex {
+        pm2_5_to_aqi(corrected(self.pm2_5, self.pm2_5_cf_1, self.humidity))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::purple::purple_data::SensorReading;
+    use crate::netatmo::Trend;
+    use crate::purple::{channel_average, combine};
+
+    fn reading(sensor_index: u64, a: f64, b: f64) -> SensorReading {
+        SensorReading {
+            sensor_index,
+            pm2_5_a: Some(a),
+            pm2_5_b: Some(b),
+            pm2_5_cf_1_a: Some(a),
+            pm2_5_cf_1_b: Some(b),
+            humidity: Some(40.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn channels() {
+        assert_eq!(channel_average(Some(10.0), Some(12.0)), Some(11.0));
+        // small absolute differences pass even when the ratio is large.
+        assert_eq!(channel_average(Some(1.0), Some(4.0)), Some(2.5));
+        assert_eq!(channel_average(Some(3.0), Some(40.0)), None);
+        assert_eq!(channel_average(Some(3.0), None), None);
+    }
+
+    #[test]
+    fn outliers_and_failed_channels() {
+        let readings = vec![
+            reading(1, 10.0, 11.0),
+            reading(2, 12.0, 12.0),
+            reading(3, 11.0, 10.0),
+            reading(4, 95.0, 97.0),
+            reading(5, 2.0, 60.0),
+        ];
+
+        let aqi = combine(&readings).unwrap();
+        assert_eq!(aqi.sensors, 3);
+        assert!((aqi.current - 11.0).abs() < 0.01);
+        assert!(combine(&[reading(5, 2.0, 60.0)]).is_none());
+    }
+
+    #[test]
+    fn trend() {
+        let mut aqi = combine(&[reading(1, 10.0, 10.0)]).unwrap();
+        aqi.one_hour = 11.0;
+        aqi.six_hour = 10.0;
+        assert_eq!(aqi.trend(), Trend::Stable);
+        aqi.one_hour = 30.0;
+        assert_eq!(aqi.trend(), Trend::Up);
+        aqi.one_hour = 4.0;
+        assert_eq!(aqi.trend(), Trend::Down);
+    }
+}
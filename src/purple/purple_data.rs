@@ -1,23 +1,83 @@
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use serde_json::Value;
 
+/// The multiple-sensor response, a table of `data` rows in `fields` order.
 #[derive(Deserialize, Debug)]
 pub struct Envelope {
-    pub sensor: Sensor,
+    pub fields: Vec<String>,
+    pub data: Vec<Vec<Value>>,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct Sensor {
-    #[serde(rename = "pm2.5")]
+impl Envelope {
+    fn value(&self, row: &[Value], name: &str) -> Option<f64> {
+        self.fields
+            .iter()
+            .position(|e| e == name)
+            .and_then(|i| row.get(i))
+            .and_then(|e| e.as_f64())
+    }
+
+    pub fn readings(&self) -> Vec<SensorReading> {
+        self.data
+            .iter()
+            .map(|row| {
+                let field = |name: &str| self.value(row, name);
+                SensorReading {
+                    sensor_index: field("sensor_index").unwrap_or_default() as u64,
+                    pm2_5_a: field("pm2.5_a"),
+                    pm2_5_b: field("pm2.5_b"),
+                    pm2_5_cf_1_a: field("pm2.5_cf_1_a"),
+                    pm2_5_cf_1_b: field("pm2.5_cf_1_b"),
+                    humidity: field("humidity"),
+                    pm2_5_1hour: field("pm2.5_60minute"),
+                    pm2_5_6hour: field("pm2.5_6hour"),
+                    pm2_5_24hour: field("pm2.5_24hour"),
+                    confidence: field("confidence"),
+                }
+            })
+            .collect()
+    }
+
+    /// Rows of the history endpoint, oldest first.
+    pub fn history(&self) -> Vec<HistoryReading> {
+        let mut history: Vec<_> = self
+            .data
+            .iter()
+            .filter_map(|row| {
+                let field = |name: &str| self.value(row, name);
+                Some(HistoryReading {
+                    time: DateTime::from_timestamp(field("time_stamp")? as i64, 0)?,
+                    pm2_5: field("pm2.5_atm")?,
+                    pm2_5_cf_1: field("pm2.5_cf_1"),
+                    humidity: field("humidity"),
+                })
+            })
+            .collect();
+        history.sort_by_key(|e| e.time);
+        history
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryReading {
+    pub time: DateTime<Utc>,
     pub pm2_5: f64,
-    pub stats: Stats,
+    pub pm2_5_cf_1: Option<f64>,
+    pub humidity: Option<f64>,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct Stats {
-    #[serde(rename = "pm2.5_60minute")]
-    pub pm2_5_1hour: f64,
-    #[serde(rename = "pm2.5_6hour")]
-    pub pm2_5_6hour: f64,
-    #[serde(rename = "pm2.5_24hour")]
-    pub pm2_5_24hour: f64,
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SensorReading {
+    pub sensor_index: u64,
+    pub pm2_5_a: Option<f64>,
+    pub pm2_5_b: Option<f64>,
+    pub pm2_5_cf_1_a: Option<f64>,
+    pub pm2_5_cf_1_b: Option<f64>,
+    pub humidity: Option<f64>,
+    pub pm2_5_1hour: Option<f64>,
+    pub pm2_5_6hour: Option<f64>,
+    pub pm2_5_24hour: Option<f64>,
+    /// PurpleAir's own 0-100 channel agreement score.
+    pub confidence: Option<f64>,
 }
@@ -1,4 +1,6 @@
+use chrono::{NaiveDate, Weekday};
 use serde::{Deserialize, Serialize};
+use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::sync::RwLock;
@@ -8,9 +10,18 @@ pub struct State {
     pub location: LocationState,
     pub netatmo: NetatmoState,
     pub purple: PurpleState,
-    pub accuweather: AccuWeatherState,
+    #[serde(default)]
+    pub accuweather: Option<AccuWeatherState>,
+    #[serde(default)]
+    pub forecast: ForecastState,
     pub calendar: CalendarState,
     pub birdnet: BirdNetState,
+    #[serde(default)]
+    pub birthdays: Option<BirthdayState>,
+    #[serde(default)]
+    pub countdowns: Vec<CountdownState>,
+    #[serde(default)]
+    pub display: DisplayState,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,22 +35,149 @@ pub struct NetatmoState {
     pub client_id: String,
     pub client_secret: String,
     pub refresh_token: String,
+    #[serde(default)]
+    pub modules: NetatmoModules,
+}
+
+/// Picks the module feeding each widget, by module id, module name, or
+/// `station name/module name`. Unset widgets use the first module of the
+/// usual type.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct NetatmoModules {
+    pub outdoor: Option<String>,
+    pub indoor: Option<String>,
+    pub wind: Option<String>,
+    pub rain: Option<String>,
+    pub pressure: Option<String>,
+}
+
+/// Either the PurpleAir API, or a sensor polled directly on the local
+/// network with `address`. Setting both is an error rather than one
+/// quietly winning.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum PurpleState {
+    Api(PurpleApiState),
+    Local(PurpleLocalState),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct PurpleState {
+#[serde(deny_unknown_fields)]
+pub struct PurpleApiState {
     pub api_key: String,
-    pub sensor_index: String,
+    pub sensor_index: Option<String>,
+    /// Sensor indexes to average, alongside `sensor_index`.
+    #[serde(default)]
+    pub sensors: Vec<String>,
+    /// Average every outdoor sensor within this many km of `location`
+    /// instead of a fixed list.
+    pub radius_km: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PurpleLocalState {
+    /// Host name or IP of the sensor, e.g. `192.168.1.40`.
+    pub address: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AccuWeatherState {
     pub api_key: String,
+    /// Calls per day to stay under; the free tier allows 50.
+    pub daily_budget: Option<u32>,
+    /// Saved by lattitude after the first geoposition lookup.
+    pub location_key: Option<AccuWeatherLocationKey>,
+}
+
+/// An AccuWeather location key, along with the coordinates it was looked
+/// up for so that moving `location` invalidates it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccuWeatherLocationKey {
+    pub lat: f64,
+    pub lon: f64,
+    pub key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ForecastState {
+    #[serde(default)]
+    pub provider: ForecastProvider,
+    /// Show NWS watches and warnings; on by default with the `nws`
+    /// provider. US locations only, whichever provider forecasts.
+    pub alerts: Option<bool>,
+}
+
+impl ForecastState {
+    pub fn alerts_enabled(&self) -> bool {
+        self.alerts.unwrap_or(self.provider == ForecastProvider::Nws)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForecastProvider {
+    /// Needs `[accuweather] api_key`.
+    #[default]
+    AccuWeather,
+    /// Free and keyless, but without moon data.
+    OpenMeteo,
+    /// The US National Weather Service; US locations only.
+    Nws,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CalendarState {
-    pub urls: Vec<String>,
+    pub urls: Vec<CalendarUrl>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum CalendarUrl {
+    Plain(String),
+    Labeled { url: String, label: String },
+}
+
+impl CalendarUrl {
+    pub fn url(&self) -> &str {
+        match self {
+            CalendarUrl::Plain(url) => url,
+            CalendarUrl::Labeled { url, .. } => url,
+        }
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            CalendarUrl::Plain(_) => None,
+            CalendarUrl::Labeled { label, .. } => Some(label),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BirthdayState {
+    /// A TOML list of `[[dates]]`, or a vCard (`.vcf`) export.
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CountdownState {
+    pub name: String,
+    #[serde(flatten)]
+    pub when: Recurrence,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    /// A single day, e.g. `date = "2026-12-20"`.
+    Date(NaiveDate),
+    /// e.g. `weekly = "Tuesday"`.
+    Weekly(Weekday),
+    /// Day of the month, e.g. `monthly = 1`.
+    Monthly(u32),
+    /// Month and day, e.g. `yearly = "12-25"`.
+    Yearly(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -47,11 +185,74 @@ pub struct BirdNetState {
     pub token: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DisplayState {
+    /// Number of upcoming events listed by the agenda.
+    pub agenda_events: usize,
+    pub rain_units: RainUnits,
+    /// Extra lines for each daily forecast row, e.g. `["night", "wind"]`.
+    pub daily_details: Vec<DailyDetail>,
+    /// How far ahead the hourly forecast looks, 12 or 24 hours.
+    pub hourly_hours: usize,
+    pub hourly_style: HourlyStyle,
+}
+
+impl Default for DisplayState {
+    fn default() -> Self {
+        Self {
+            agenda_events: 12,
+            rain_units: RainUnits::Inches,
+            daily_details: vec![],
+            hourly_hours: 12,
+            hourly_style: HourlyStyle::Columns,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DailyDetail {
+    /// Overnight conditions and low.
+    Night,
+    /// Wind speed and direction, with gusts.
+    Wind,
+    /// Snowfall shown separately, and taken out of the precipitation total.
+    Snow,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HourlyStyle {
+    /// An icon, temperature and chance of rain per hour.
+    Columns,
+    /// A temperature line over chance-of-rain bars.
+    Graph,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RainUnits {
+    Inches,
+    Millimeters,
+}
+
+impl RainUnits {
+    pub fn format(&self, mm: f32) -> String {
+        match self {
+            RainUnits::Inches => format!("{:.2}\"", mm / 25.4),
+            RainUnits::Millimeters => format!("{:.1}mm", mm),
+        }
+    }
+}
+
+const CONFIG_FILE: &str = "lattitude.toml";
+
 static STATE: RwLock<Option<State>> = RwLock::new(None);
 
 pub fn state() -> State {
     if STATE.read().unwrap().is_none() {
-        let mut config = File::open("lattitude.toml").unwrap();
+        let mut config = File::open(CONFIG_FILE).unwrap();
         let mut data = String::new();
         let _ = config.read_to_string(&mut data);
 
@@ -63,21 +264,42 @@ pub fn state() -> State {
     STATE.read().unwrap().clone().unwrap()
 }
 
-pub fn update_state<F: FnOnce(&mut State)>(updater: F) {
+/// Re-reads the config file, picking up changes saved by another process
+/// such as `lattitude netatmo login`.
+pub fn reload_state() -> Result<State, anyhow::Error> {
+    let mut data = String::new();
+    File::open(CONFIG_FILE)?.read_to_string(&mut data)?;
+
+    let state: State = toml::from_str(&data)?;
+
+    STATE.write().unwrap().replace(state.clone());
+
+    Ok(state)
+}
+
+/// Applies `updater` and saves the result. The new config is written
+/// beside the old one and renamed over it, so an interrupted write can't
+/// leave a truncated config behind.
+pub fn update_state<F: FnOnce(&mut State)>(updater: F) -> Result<(), anyhow::Error> {
     let mut state = state();
     updater(&mut state);
 
-    let toml = toml::to_string_pretty(&state).unwrap();
-    let mut config = File::create("latitude.toml").unwrap();
+    let toml = toml::to_string_pretty(&state)?;
 
-    config.write_all(toml.as_bytes()).unwrap();
+    let staged = format!("{}.tmp", CONFIG_FILE);
+    let mut config = File::create(&staged)?;
+    config.write_all(toml.as_bytes())?;
+    config.sync_all()?;
+    fs::rename(&staged, CONFIG_FILE)?;
 
     STATE.write().unwrap().replace(state);
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
-    use crate::state::{state, update_state, State};
+    use crate::state::{state, update_state, PurpleState, State};
     use std::fs::File;
     use std::io::Read;
 
@@ -110,4 +332,16 @@ mod test {
 
          */
     }
+
+    #[test]
+    fn purple_modes() {
+        let api: PurpleState = toml::from_str("api_key = \"key\"\nsensors = [\"1\"]").unwrap();
+        assert!(matches!(api, PurpleState::Api(_)));
+
+        let local: PurpleState = toml::from_str("address = \"192.168.1.40\"").unwrap();
+        assert!(matches!(local, PurpleState::Local(_)));
+
+        let both = toml::from_str::<PurpleState>("api_key = \"key\"\naddress = \"192.168.1.40\"");
+        assert!(both.is_err());
+    }
 }